@@ -0,0 +1,84 @@
+use once_cell::sync::Lazy;
+use std::{env, path::PathBuf, process};
+
+const USAGE: &str = "Usage: nokogiri-rs [OPTIONS] [COMMAND]
+
+Commands:
+  check-config          Validate the configuration, report every problem and exit
+
+Options:
+  -c, --config <PATH>   Configuration file to load [env: NOKOGIRI_CONFIG] [default: ./config.toml]
+      --no-config-file  Run on the built-in defaults and NOKOGIRI__SECTION__KEY environment variables only
+                        [env: NOKOGIRI_NO_CONFIG_FILE]
+  -h, --help            Print this help";
+
+#[derive(Debug)]
+pub enum ConfigFile {
+    /// ./config.toml, created from the defaults if it does not exist yet
+    Default,
+    /// A file given explicitly, which has to exist
+    Path(PathBuf),
+    /// No file at all, only the defaults and the environment
+    None,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Serve,
+    CheckConfig,
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub command: Command,
+    pub config_file: ConfigFile,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut config_path = None;
+        let mut no_config_file = false;
+        let mut command = Command::Serve;
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-c" | "--config" => match args.next() {
+                    Some(path) => config_path = Some(PathBuf::from(path)),
+                    None => exit_with_usage(&format!("{} requires a path", arg)),
+                },
+                "--no-config-file" => no_config_file = true,
+                "check-config" => command = Command::CheckConfig,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    process::exit(0);
+                }
+                _ => match arg.strip_prefix("--config=") {
+                    Some(path) => config_path = Some(PathBuf::from(path)),
+                    None => exit_with_usage(&format!("Unknown argument '{}'", arg)),
+                },
+            }
+        }
+
+        // Command line arguments take precedence over the environment
+        let config_path = config_path.or_else(|| env::var_os("NOKOGIRI_CONFIG").filter(|p| !p.is_empty()).map(PathBuf::from));
+        let no_config_file = no_config_file ||
+            env::var("NOKOGIRI_NO_CONFIG_FILE").is_ok_and(|v| matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"));
+
+        let config_file = match (config_path, no_config_file) {
+            (Some(_), true) => exit_with_usage("--config and --no-config-file cannot be used together"),
+            (Some(path), false) => ConfigFile::Path(path),
+            (None, true) => ConfigFile::None,
+            (None, false) => ConfigFile::Default,
+        };
+
+        Self { command, config_file }
+    }
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+pub static ARGS: Lazy<Args> = Lazy::new(Args::parse);
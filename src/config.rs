@@ -1,29 +1,88 @@
-use crate::resource;
-use config::{Config, File, FileFormat};
+use crate::{
+    cli::{self, ConfigFile},
+    resource,
+};
+use arc_swap::ArcSwap;
+use config::{Config, Environment, File, FileFormat};
 use once_cell::sync::Lazy;
 use serde::Deserialize;
-use std::{fs, path::Path, process};
+use std::{env, fs, path::Path, process};
+
+pub mod reload;
+pub mod validate;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct IServer {
     pub host: String,
     pub port: u16,
+    pub shutdown_timeout: u64,
+    pub shutdown_delay: u64,
+    pub config_watch_interval: u64,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct IGeneral {
     pub ignore_robots_txt: bool,
     pub default_lang: String,
+    pub accept_language: bool,
     pub max_redirect_hops: u32,
     pub response_timeout: u64,
     pub operation_timeout: u64,
     pub content_length_limit: String,
+    pub head_read_limit: String,
+    pub batch_max_urls: usize,
+    pub batch_concurrency: usize,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ISecurity {
     pub secret_key: String,
+    pub metrics_token: String,
+    pub admin_token: String,
     pub block_non_global_ips: bool,
+    pub allowed_hosts: Vec<String>,
+    pub denied_hosts: Vec<String>,
+    pub allowed_ip_ranges: Vec<String>,
+    pub denied_ip_ranges: Vec<String>,
+    pub allowed_ports: Vec<String>,
+    pub denied_ports: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ICanonicalize {
+    pub enabled: bool,
+    pub drop_fragment: bool,
+    pub sort_query: bool,
+    pub strip_params: Vec<String>,
+    #[serde(default)]
+    pub rules: Vec<ICanonicalizeRule>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct IProxy {
+    pub default: String,
+    pub no_proxy: Vec<String>,
+    #[serde(default)]
+    pub rules: Vec<IProxyRule>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct IProxyRule {
+    pub hosts: Vec<String>,
+    pub proxy: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ICanonicalizeRule {
+    pub domain: String,
+    #[serde(default)]
+    pub strip_www: bool,
+    #[serde(default)]
+    pub strip_trailing_slash: bool,
+    #[serde(default)]
+    pub strip_params: Vec<String>,
+    #[serde(default)]
+    pub keep_params: Vec<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -44,12 +103,29 @@ pub struct IDebug {
 #[derive(Deserialize, Debug, Clone)]
 pub struct ICache {
     pub enabled: bool,
+    pub backend: String,
+    pub memory_capacity: usize,
+    pub memory_ttl: u64,
+    pub mode: String,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub nodes: Vec<String>,
+    pub sentinel_master: String,
     pub host: String,
     pub port: u16,
+    pub min_ttl: u64,
+    pub max_ttl: u64,
+    pub stale_ttl: u64,
+    pub transient_error_ttl: u64,
+    pub permanent_error_ttl: u64,
     pub prefix: Option<String>,
     pub db: Option<u32>,
     pub username: Option<String>,
     pub password: Option<String>,
+    pub connection_timeout: u64,
+    pub command_timeout: u64,
+    pub coalesce_across_instances: bool,
+    pub coalesce_lock_ttl: u64,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -57,29 +133,103 @@ pub struct AppConfig {
     pub server: IServer,
     pub general: IGeneral,
     pub security: ISecurity,
+    pub proxy: IProxy,
     pub plugins: IPlugins,
+    pub canonicalize: ICanonicalize,
     pub cache: ICache,
     pub sentry: Option<ISentry>,
     pub debug: Option<IDebug>,
 }
 
+pub const DEFAULT_CONFIG_PATH: &str = "./config.toml";
+
+// Environment variables named NOKOGIRI__SECTION__KEY override the corresponding key, e.g. NOKOGIRI__SECURITY__SECRET_KEY
+const ENV_PREFIX: &str = "NOKOGIRI";
+const ENV_SEPARATOR: &str = "__";
+
+// Keys holding lists, which are given as comma separated values in the environment
+const ENV_LIST_KEYS: [&str; 10] = [
+    "plugins.disabled",
+    "canonicalize.strip_params",
+    "cache.nodes",
+    "proxy.no_proxy",
+    "security.allowed_hosts",
+    "security.denied_hosts",
+    "security.allowed_ip_ranges",
+    "security.denied_ip_ranges",
+    "security.allowed_ports",
+    "security.denied_ports",
+];
+
 impl AppConfig {
     pub fn new() -> Result<Self, config::ConfigError> {
-        if !Path::new("./config.toml").exists() {
-            let default_config = resource::DEFAULT_CONFIG_TOML;
-            fs::write("./config.toml", default_config).expect("Failed to create default config file");
-            println!("Created configration file at ./config.toml. Please check it before running the application.");
-            process::exit(0);
+        Self::load(true)?.try_deserialize::<AppConfig>()
+    }
+
+    /// Merges the defaults, the config file and the environment without deserializing them.
+    /// A missing ./config.toml is created from the defaults if `create_default` is set, which exits the process.
+    pub fn load(create_default: bool) -> Result<Config, config::ConfigError> {
+        let mut builder = Config::builder().add_source(File::from_str(str::from_utf8(resource::DEFAULT_CONFIG_TOML).unwrap(), FileFormat::Toml));
+
+        let path = match &cli::ARGS.config_file {
+            ConfigFile::Default => {
+                if create_default && !Path::new(DEFAULT_CONFIG_PATH).exists() {
+                    create_default_config();
+                }
+                Some(Path::new(DEFAULT_CONFIG_PATH))
+            }
+            ConfigFile::Path(path) => Some(path.as_path()),
+            ConfigFile::None => None,
+        };
+
+        if let Some(path) = path {
+            if !path.exists() {
+                return Err(config::ConfigError::Message(format!(
+                    "Configuration file {} does not exist",
+                    path.display()
+                )));
+            }
+            builder = builder.add_source(File::from(path).format(FileFormat::Toml));
         }
 
-        let config = Config::builder()
-            .add_source(File::from_str(str::from_utf8(resource::DEFAULT_CONFIG_TOML).unwrap(), FileFormat::Toml))
-            .add_source(File::with_name("./config.toml"))
-            .build()
-            .expect("Failed to load configuration");
+        // Values are kept as strings and converted when deserializing, so that a secret like "0123" is not read as a number
+        builder = builder.add_source(
+            Environment::with_prefix(ENV_PREFIX)
+                .prefix_separator(ENV_SEPARATOR)
+                .separator(ENV_SEPARATOR),
+        );
+        for key in ENV_LIST_KEYS {
+            if let Ok(value) = env::var(env_var_name(key)) {
+                let items: Vec<String> = value.split(',').map(str::trim).filter(|v| !v.is_empty()).map(String::from).collect();
+                builder = builder.set_override(key, items)?;
+            }
+        }
+
+        builder.build()
+    }
+}
 
-        config.try_deserialize::<AppConfig>()
+fn create_default_config() -> ! {
+    if let Err(e) = fs::write(DEFAULT_CONFIG_PATH, resource::DEFAULT_CONFIG_TOML) {
+        eprintln!(
+            "Failed to create default config file at {}: {}\nUse --config to point to an existing file, or --no-config-file to run on the defaults and environment variables only.",
+            DEFAULT_CONFIG_PATH, e
+        );
+        process::exit(1);
     }
+
+    println!(
+        "Created configration file at {}. Please check it before running the application.",
+        DEFAULT_CONFIG_PATH
+    );
+    process::exit(0);
+}
+
+/// Name of the environment variable overriding `key`, e.g. NOKOGIRI__CACHE__PASSWORD for cache.password
+fn env_var_name(key: &str) -> String {
+    format!("{}{}{}", ENV_PREFIX, ENV_SEPARATOR, key.replace('.', ENV_SEPARATOR).to_uppercase())
 }
 
-pub static CONFIG: Lazy<AppConfig> = Lazy::new(|| AppConfig::new().expect("Failed to initialize application configuration"));
+/// The running configuration. Read it with `CONFIG.load()` for every use instead of keeping it around, so that reloads take effect.
+pub static CONFIG: Lazy<ArcSwap<AppConfig>> =
+    Lazy::new(|| ArcSwap::from_pointee(AppConfig::new().expect("Failed to initialize application configuration")));
@@ -0,0 +1,105 @@
+use crate::{
+    cli::{self, ConfigFile},
+    config::{AppConfig, CONFIG, DEFAULT_CONFIG_PATH, validate},
+    core::request,
+};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::signal;
+
+/// Loads the configuration again and swaps it in. An invalid configuration is rejected and the running one stays in place.
+pub fn reload() -> bool {
+    let report = validate::check(false);
+    for problem in &report.problems {
+        match problem.severity {
+            validate::Severity::Error => tracing::error!("{}", problem),
+            validate::Severity::Warning => tracing::warn!("{}", problem),
+        }
+    }
+
+    let Some(config) = report.config else {
+        tracing::error!("Rejected the new configuration, keeping the running one");
+        return false;
+    };
+
+    warn_restart_required(&CONFIG.load(), &config);
+
+    request::reload(&config);
+    CONFIG.store(Arc::new(config));
+
+    tracing::info!("Reloaded configuration");
+    true
+}
+
+// These are only read once on startup
+fn warn_restart_required(current: &AppConfig, new: &AppConfig) {
+    let changed = [
+        ("server.host", current.server.host != new.server.host),
+        ("server.port", current.server.port != new.server.port),
+        (
+            "server.config_watch_interval",
+            current.server.config_watch_interval != new.server.config_watch_interval,
+        ),
+        ("cache", format!("{:?}", current.cache) != format!("{:?}", new.cache)),
+        ("sentry", format!("{:?}", current.sentry) != format!("{:?}", new.sentry)),
+        ("debug", format!("{:?}", current.debug) != format!("{:?}", new.debug)),
+    ];
+
+    for (key, _) in changed.iter().filter(|(_, changed)| *changed) {
+        tracing::warn!("Changes to {} take effect after a restart", key);
+    }
+}
+
+/// Reloads on SIGHUP, and whenever the config file changes if `server.config_watch_interval` is set
+pub async fn watch() {
+    tokio::join!(watch_signal(), watch_file());
+}
+
+#[cfg(unix)]
+async fn watch_signal() {
+    let mut hangup = match signal::unix::signal(signal::unix::SignalKind::hangup()) {
+        Ok(s) => s,
+        Err(err) => {
+            tracing::error!("Failed to listen for SIGHUP: {}", err);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        tracing::info!("Received SIGHUP, reloading configuration");
+        reload();
+    }
+}
+
+#[cfg(not(unix))]
+async fn watch_signal() {}
+
+async fn watch_file() {
+    let interval = CONFIG.load().server.config_watch_interval;
+    let path = match &cli::ARGS.config_file {
+        ConfigFile::Default => PathBuf::from(DEFAULT_CONFIG_PATH),
+        ConfigFile::Path(path) => path.clone(),
+        ConfigFile::None => return,
+    };
+
+    if interval == 0 {
+        return;
+    }
+
+    let modified = |path: &PathBuf| -> Option<SystemTime> { fs::metadata(path).and_then(|m| m.modified()).ok() };
+    let mut last_modified = modified(&path);
+    loop {
+        tokio::time::sleep(Duration::from_millis(interval)).await;
+
+        let current = modified(&path);
+        if current != last_modified {
+            last_modified = current;
+            tracing::info!("{} changed, reloading configuration", path.display());
+            reload();
+        }
+    }
+}
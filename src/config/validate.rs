@@ -0,0 +1,397 @@
+use crate::{
+    config::{AppConfig, ICache, ICanonicalize, IDebug, IGeneral, IPlugins, IProxy, ISecurity, ISentry, IServer},
+    core::{
+        request::{
+            pattern::HostPattern,
+            policy::{build_acl, parse_ip_range, parse_port_range},
+            proxy::parse_proxy_url,
+        },
+        summary::handler::CUSTOM_HANDLERS,
+    },
+    resource,
+};
+use config::{Config, File, FileFormat, Map, Value, ValueKind};
+use language_tags::LanguageTag;
+use parse_size::parse_size;
+use redis::IntoConnectionInfo;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use tracing_subscriber::filter::LevelFilter;
+
+// Keys that are only commented out in the default config, and therefore cannot be discovered from it
+const OPTIONAL_KEYS: &[&str] = &[
+    "cache.prefix",
+    "cache.db",
+    "cache.username",
+    "cache.password",
+    "cache.url",
+    "cache.nodes",
+    "proxy.rules",
+    "canonicalize.rules",
+    "sentry.dsn",
+    "debug.log_level",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+pub struct Problem {
+    pub severity: Severity,
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.key, self.message)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub problems: Vec<Problem>,
+    // Only set if there are no errors
+    pub config: Option<AppConfig>,
+}
+
+impl Report {
+    fn error(&mut self, key: &str, message: impl Into<String>) {
+        self.problems.push(Problem {
+            severity: Severity::Error,
+            key: key.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn warning(&mut self, key: &str, message: impl Into<String>) {
+        self.problems.push(Problem {
+            severity: Severity::Warning,
+            key: key.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.problems.iter().any(|p| p.severity == Severity::Error)
+    }
+}
+
+/// Loads the configuration the same way the server does and collects every problem with it instead of stopping at the first one
+pub fn check(create_default: bool) -> Report {
+    let mut report = Report::default();
+
+    let raw = match AppConfig::load(create_default) {
+        Ok(raw) => raw,
+        Err(e) => {
+            report.error("", e.to_string());
+            return report;
+        }
+    };
+
+    check_unknown_keys(&raw, &mut report);
+
+    // Sections are deserialized one by one so that a mistake in one of them does not hide the others
+    let server = section::<IServer>(&raw, "server", &mut report);
+    let general = section::<IGeneral>(&raw, "general", &mut report);
+    let security = section::<ISecurity>(&raw, "security", &mut report);
+    let proxy = section::<IProxy>(&raw, "proxy", &mut report);
+    let plugins = section::<IPlugins>(&raw, "plugins", &mut report);
+    let canonicalize = section::<ICanonicalize>(&raw, "canonicalize", &mut report);
+    let cache = section::<ICache>(&raw, "cache", &mut report);
+    let sentry = optional_section::<ISentry>(&raw, "sentry", &mut report);
+    let debug = optional_section::<IDebug>(&raw, "debug", &mut report);
+
+    if let Some(server) = server &&
+        server.host.trim().is_empty()
+    {
+        report.error("server.host", "must not be empty");
+    }
+
+    if let Some(general) = general {
+        check_general(&general, &mut report);
+    }
+    if let Some(security) = security {
+        check_security(&security, &mut report);
+    }
+    if let Some(proxy) = proxy {
+        check_proxy(&proxy, &mut report);
+    }
+    if let Some(plugins) = plugins {
+        check_plugins(&plugins, &mut report);
+    }
+    if let Some(canonicalize) = canonicalize {
+        check_canonicalize(&canonicalize, &mut report);
+    }
+    if let Some(cache) = cache {
+        check_cache(&cache, &mut report);
+    }
+
+    if let Some(Some(sentry)) = sentry &&
+        let Some(dsn) = sentry.dsn &&
+        let Err(e) = sentry::IntoDsn::into_dsn(dsn)
+    {
+        report.error("sentry.dsn", format!("invalid DSN: {}", e));
+    }
+
+    if let Some(Some(debug)) = debug &&
+        let Some(level) = debug.log_level &&
+        level.parse::<LevelFilter>().is_err()
+    {
+        report.error(
+            "debug.log_level",
+            format!("unknown log level '{}', expected one of off, error, warn, info, debug, trace", level),
+        );
+    }
+
+    if !report.has_errors() {
+        match raw.try_deserialize::<AppConfig>() {
+            Ok(config) => report.config = Some(config),
+            Err(e) => report.error("", e.to_string()),
+        }
+    }
+
+    report
+}
+
+fn section<T: DeserializeOwned>(raw: &Config, key: &str, report: &mut Report) -> Option<T> {
+    let value = optional_section(raw, key, report)?;
+    if value.is_none() {
+        report.error(key, "missing section");
+    }
+    value
+}
+
+// `None` if the section is invalid, `Some(None)` if it is absent
+fn optional_section<T: DeserializeOwned>(raw: &Config, key: &str, report: &mut Report) -> Option<Option<T>> {
+    let value = match raw.get::<Value>(key) {
+        Ok(value) => value,
+        Err(config::ConfigError::NotFound(_)) => return Some(None),
+        Err(e) => {
+            report.error(key, e.to_string());
+            return None;
+        }
+    };
+
+    // Tracks the path into the section, so that the error points at the offending key rather than the whole section
+    match serde_path_to_error::deserialize::<_, T>(value) {
+        Ok(value) => Some(Some(value)),
+        Err(e) => {
+            let path = e.path().to_string();
+            let key = if path == "." { key.to_string() } else { format!("{}.{}", key, path) };
+            report.error(&key, e.into_inner().to_string());
+            None
+        }
+    }
+}
+
+fn check_unknown_keys(raw: &Config, report: &mut Report) {
+    let Ok(defaults) = Config::builder()
+        .add_source(File::from_str(str::from_utf8(resource::DEFAULT_CONFIG_TOML).unwrap(), FileFormat::Toml))
+        .build()
+        .and_then(|c| c.try_deserialize::<Map<String, Value>>())
+    else {
+        return;
+    };
+
+    let mut known = Vec::new();
+    collect_keys(&defaults, "", &mut known);
+    known.extend(OPTIONAL_KEYS.iter().map(|k| k.to_string()));
+
+    let Ok(actual) = raw.clone().try_deserialize::<Map<String, Value>>() else {
+        return;
+    };
+    let mut keys = Vec::new();
+    collect_keys(&actual, "", &mut keys);
+    keys.sort();
+
+    for key in keys {
+        // Everything below an optional table, such as the fields of canonicalize.rules, is checked when deserializing
+        let is_known = known.contains(&key) || OPTIONAL_KEYS.iter().any(|k| key.starts_with(&format!("{}.", k)));
+        if !is_known {
+            report.warning(&key, "unknown key, it is ignored");
+        }
+    }
+}
+
+fn collect_keys(table: &Map<String, Value>, prefix: &str, keys: &mut Vec<String>) {
+    for (name, value) in table {
+        let key = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        match &value.kind {
+            ValueKind::Table(inner) => {
+                // Sections themselves are not keys, only what is inside of them
+                if prefix.is_empty() {
+                    collect_keys(inner, &key, keys);
+                } else {
+                    keys.push(key);
+                }
+            }
+            _ => keys.push(key),
+        }
+    }
+}
+
+fn check_general(general: &IGeneral, report: &mut Report) {
+    if let Err(e) = parse_size(&general.content_length_limit) {
+        report.error(
+            "general.content_length_limit",
+            format!("invalid size '{}': {}", general.content_length_limit, e),
+        );
+    }
+
+    if let Err(e) = parse_size(&general.head_read_limit) {
+        report.error("general.head_read_limit", format!("invalid size '{}': {}", general.head_read_limit, e));
+    }
+
+    if LanguageTag::parse(&general.default_lang).is_err() {
+        report.error("general.default_lang", format!("'{}' is not a valid language tag", general.default_lang));
+    }
+
+    if general.response_timeout == 0 {
+        report.error("general.response_timeout", "must be greater than 0");
+    }
+    if general.operation_timeout == 0 {
+        report.error("general.operation_timeout", "must be greater than 0");
+    }
+    if general.batch_concurrency == 0 {
+        report.error("general.batch_concurrency", "must be greater than 0");
+    }
+}
+
+fn check_security(security: &ISecurity, report: &mut Report) {
+    if security.secret_key.trim() != security.secret_key {
+        report.warning("security.secret_key", "has leading or trailing whitespace, which is part of the key");
+    }
+
+    check_host_patterns("security.allowed_hosts", &security.allowed_hosts, report);
+    check_host_patterns("security.denied_hosts", &security.denied_hosts, report);
+
+    for (key, ranges) in [
+        ("security.allowed_ip_ranges", &security.allowed_ip_ranges),
+        ("security.denied_ip_ranges", &security.denied_ip_ranges),
+    ] {
+        for (i, range) in ranges.iter().enumerate() {
+            if let Err(e) = parse_ip_range(range) {
+                report.error(&format!("{}[{}]", key, i), e);
+            }
+        }
+    }
+    for (key, ports) in [
+        ("security.allowed_ports", &security.allowed_ports),
+        ("security.denied_ports", &security.denied_ports),
+    ] {
+        for (i, port) in ports.iter().enumerate() {
+            if let Err(e) = parse_port_range(port) {
+                report.error(&format!("{}[{}]", key, i), e);
+            }
+        }
+    }
+
+    // The ACL rejects some combinations of entries on its own, such as a range that is both allowed and denied
+    if let Err(e) = build_acl(security) {
+        report.error("security", format!("the access control lists cannot be built: {}", e));
+    }
+}
+
+fn check_proxy(proxy: &IProxy, report: &mut Report) {
+    if let Err(e) = parse_proxy_url(&proxy.default) {
+        report.error("proxy.default", e);
+    }
+
+    check_host_patterns("proxy.no_proxy", &proxy.no_proxy, report);
+
+    for (i, rule) in proxy.rules.iter().enumerate() {
+        if rule.hosts.is_empty() {
+            report.error(&format!("proxy.rules[{}].hosts", i), "must not be empty");
+        }
+        check_host_patterns(&format!("proxy.rules[{}].hosts", i), &rule.hosts, report);
+
+        if let Err(e) = parse_proxy_url(&rule.proxy) {
+            report.error(&format!("proxy.rules[{}].proxy", i), e);
+        }
+    }
+}
+
+fn check_host_patterns(key: &str, patterns: &[String], report: &mut Report) {
+    for (i, pattern) in patterns.iter().enumerate() {
+        if let Err(e) = HostPattern::parse(pattern) {
+            report.error(&format!("{}[{}]", key, i), e);
+        }
+    }
+}
+
+fn check_plugins(plugins: &IPlugins, report: &mut Report) {
+    for id in &plugins.disabled {
+        if !CUSTOM_HANDLERS.iter().any(|handler| handler.id() == id) {
+            let known: Vec<&str> = CUSTOM_HANDLERS.iter().map(|handler| handler.id()).collect();
+            report.warning(
+                "plugins.disabled",
+                format!("'{}' does not match any handler, expected one of {}", id, known.join(", ")),
+            );
+        }
+    }
+}
+
+fn check_canonicalize(canonicalize: &ICanonicalize, report: &mut Report) {
+    for (i, rule) in canonicalize.rules.iter().enumerate() {
+        if rule.domain.trim().is_empty() {
+            report.error(&format!("canonicalize.rules[{}].domain", i), "must not be empty");
+        }
+    }
+}
+
+fn check_cache(cache: &ICache, report: &mut Report) {
+    if !matches!(cache.backend.as_str(), "memory" | "redis" | "tiered") {
+        report.error(
+            "cache.backend",
+            format!("unknown backend '{}', expected one of memory, redis, tiered", cache.backend),
+        );
+    }
+
+    if !matches!(cache.mode.as_str(), "standalone" | "cluster" | "sentinel") {
+        report.error(
+            "cache.mode",
+            format!("unknown mode '{}', expected one of standalone, cluster, sentinel", cache.mode),
+        );
+    }
+
+    if cache.mode == "sentinel" && cache.nodes.is_empty() {
+        report.error("cache.nodes", "sentinel mode requires the addresses of the sentinels");
+    }
+
+    if let Some(url) = &cache.url &&
+        let Err(e) = url.as_str().into_connection_info()
+    {
+        report.error("cache.url", format!("invalid connection URL: {}", e));
+    }
+
+    for (i, node) in cache.nodes.iter().enumerate() {
+        if let Err(e) = node.as_str().into_connection_info() {
+            report.error(&format!("cache.nodes[{}]", i), format!("invalid connection URL: {}", e));
+        }
+    }
+
+    if cache.min_ttl > cache.max_ttl {
+        report.error("cache.min_ttl", format!("must not be greater than cache.max_ttl ({})", cache.max_ttl));
+    }
+}
@@ -1,4 +1,6 @@
 pub mod cache;
+pub mod error;
+pub mod metrics;
 pub mod request;
 pub mod sentry;
 pub mod summary;
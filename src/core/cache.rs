@@ -1,127 +1,233 @@
-use crate::config::CONFIG;
+use crate::{config::CONFIG, core::metrics};
 use once_cell::sync::Lazy;
-use redis::{Client, Commands};
-use std::process;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
 use xxhash_rust::xxh64::xxh64;
 
-static REDIS_CLIENT: Lazy<Option<Client>> = Lazy::new(|| {
-    let cache_config = &CONFIG.cache;
+mod backend;
+mod memory;
+mod redis;
+mod tiered;
+
+static BACKEND: Lazy<Option<Box<dyn backend::CacheBackend>>> = Lazy::new(|| {
+    // The backend keeps its connection for the lifetime of the process, changes to it require a restart
+    let config = CONFIG.load();
+    let cache_config = &config.cache;
     if !cache_config.enabled {
         tracing::info!("Cache is disabled");
         return None;
     }
 
-    let mut connection_url = "redis://".to_string();
-    if cache_config.username.is_some() || cache_config.password.is_some() {
-        let username = cache_config.username.as_ref().map_or("", String::as_str);
-        let password = cache_config.password.as_ref().map_or("", String::as_str);
-        connection_url.push_str(&format!("{}:{}@", username, password));
-    }
-
-    connection_url.push_str(&format!("{}:{}", cache_config.host, cache_config.port));
-    if let Some(db) = cache_config.db {
-        connection_url.push_str(&format!("/{}", db));
-    }
-
-    let client = match Client::open(connection_url) {
-        Ok(c) => c,
-        Err(e) => {
-            tracing::error!("Failed to create Redis client: {}", e);
-            process::exit(1);
-        }
-    };
-
-    // Test connection
-    match client.get_connection() {
-        Ok(mut conn) => {
-            if let Err(e) = conn.ping::<String>() {
-                tracing::error!("Failed to connect to Redis server: {}", e);
-                process::exit(1);
+    let backend: Box<dyn backend::CacheBackend> = match cache_config.backend.as_str() {
+        "memory" => Box::new(memory::MemoryBackend::new(cache_config.memory_capacity)),
+        "redis" | "tiered" => {
+            let redis = match redis::RedisBackend::new(cache_config) {
+                Ok(r) => r,
+                Err(e) => {
+                    tracing::error!("Failed to create Redis client, continuing without cache: {}", e);
+                    return None;
+                }
+            };
+
+            if cache_config.backend == "tiered" {
+                let memory = memory::MemoryBackend::new(cache_config.memory_capacity);
+                Box::new(tiered::TieredBackend::new(memory, redis, cache_config.memory_ttl))
+            } else {
+                Box::new(redis)
             }
-            tracing::info!("Connected to Redis server");
-            Some(client)
         }
-        Err(e) => {
-            tracing::error!("Failed to get Redis connection: {}", e);
-            process::exit(1);
+        other => {
+            tracing::error!("Unknown cache backend '{}', continuing without cache", other);
+            return None;
         }
-    }
+    };
+
+    tracing::info!("Using {} cache backend", backend.id());
+    Some(backend)
 });
 
-fn gen_key(category: &str, identifier: &str) -> String {
+pub async fn init() {
+    if let Some(backend) = BACKEND.as_ref() {
+        backend.init().await;
+    }
+}
+
+pub async fn close() {
+    if let Some(backend) = BACKEND.as_ref() {
+        backend.close().await;
+    }
+}
+
+/// Pings the cache backend, returns `None` if the cache is disabled
+pub async fn ping() -> Option<anyhow::Result<()>> {
+    Some(BACKEND.as_ref()?.ping().await)
+}
+
+fn key_prefix(category: &str) -> String {
     let mut key = String::new();
 
-    if let Some(prefix) = &CONFIG.cache.prefix {
+    if let Some(prefix) = &CONFIG.load().cache.prefix {
         key.push_str(prefix);
         key.push(':');
     }
 
-    key.push_str(&format!("nokogiri:{}:{}", category, xxh64(identifier.as_bytes(), 0).to_string().as_str()));
+    key.push_str(&format!("nokogiri:{}:", category));
     key
 }
 
-pub fn get_summarize_cache(url: &str, lang: Option<String>) -> Option<String> {
-    let mut connection = REDIS_CLIENT.as_ref()?.get_connection().ok()?;
-    let key = gen_key(
-        "summarize",
-        format!("{}:{}", url, lang.unwrap_or(CONFIG.general.default_lang.clone())).as_str(),
-    );
+fn gen_key(category: &str, identifier: &str) -> String {
+    let mut key = key_prefix(category);
+    key.push_str(xxh64(identifier.as_bytes(), 0).to_string().as_str());
+    key
+}
+
+async fn get(category: &str, key: &str) -> Option<String> {
+    let backend = BACKEND.as_ref()?;
+    let value = backend.get(key).await;
+
+    let operation = if value.is_some() { "hit" } else { "miss" };
+    metrics::CACHE_OPERATIONS_TOTAL.with_label_values(&[category, operation]).inc();
+    value
+}
+
+async fn set(category: &str, key: &str, content: &str, ttl: u64) {
+    if let Some(backend) = BACKEND.as_ref() {
+        backend.set(key, content, ttl).await;
+        metrics::CACHE_OPERATIONS_TOTAL.with_label_values(&[category, "set"]).inc();
+    }
+}
+
+/// Summaries fetched with per-request options that differ from the defaults are stored under their own `variant`
+pub fn summarize_key(url: &str, lang: Option<String>, variant: Option<&str>) -> String {
+    let mut identifier = format!("{}:{}", url, lang.unwrap_or_else(|| CONFIG.load().general.default_lang.clone()));
+    if let Some(variant) = variant {
+        identifier.push(':');
+        identifier.push_str(variant);
+    }
+
+    gen_key("summarize", &identifier)
+}
+
+pub async fn get_summarize_cache(url: &str, lang: Option<String>, variant: Option<&str>) -> Option<String> {
+    let key = summarize_key(url, lang, variant);
 
     tracing::debug!("Checking cache for key: {}", key);
-    connection.get(&key).ok()
+    get("summarize", &key).await
 }
 
-pub fn set_summarize_cache(url: &str, lang: Option<String>, content: &str, ttl: &u64) {
+pub async fn set_summarize_cache(url: &str, lang: Option<String>, variant: Option<&str>, content: &str, ttl: &u64) {
     if ttl == &0 {
         tracing::debug!("TTL is 0, not setting cache");
         return;
     }
 
-    let mut connection = match REDIS_CLIENT.as_ref().and_then(|c| c.get_connection().ok()) {
-        Some(conn) => conn,
-        None => return,
-    };
-
-    let key = gen_key(
-        "summarize",
-        format!("{}:{}", url, lang.unwrap_or(CONFIG.general.default_lang.clone())).as_str(),
-    );
+    let key = summarize_key(url, lang, variant);
 
-    let mut ttl = ttl;
-    if ttl > &86400 {
-        tracing::debug!("TTL is greater than 86400 seconds (1 day), capping to 86400");
-        ttl = &86400;
+    // Entries are kept for their stale period on top of the freshness lifetime
+    let max_ttl = {
+        let config = CONFIG.load();
+        config.cache.max_ttl + config.cache.stale_ttl
+    };
+    let mut ttl = *ttl;
+    if ttl > max_ttl {
+        tracing::debug!("TTL is greater than {} seconds, capping to {}", max_ttl, max_ttl);
+        ttl = max_ttl;
     }
 
     tracing::debug!("Setting cache for key: {} with TTL: {} seconds", key, ttl);
-    match connection.set_ex::<&String, &str, String>(&key, content, *ttl) {
-        Ok(_) => (),
-        Err(e) => {
-            tracing::error!("Failed to set cache for key {}: {}", key, e);
+    set("summarize", &key, content, ttl).await;
+}
+
+pub async fn delete_summarize_cache(url: &str, lang: Option<String>, variant: Option<&str>) -> bool {
+    let Some(backend) = BACKEND.as_ref() else { return false };
+    let key = summarize_key(url, lang, variant);
+
+    tracing::debug!("Deleting cache for key: {}", key);
+    backend.delete(&key).await
+}
+
+pub async fn delete_cache_key(key: &str) -> bool {
+    let Some(backend) = BACKEND.as_ref() else { return false };
+    backend.delete(key).await
+}
+
+/// Lists every summarize cache entry with its value, lock keys included
+pub async fn scan_summarize_cache() -> Vec<(String, String)> {
+    let Some(backend) = BACKEND.as_ref() else { return vec![] };
+    let mut entries = Vec::new();
+
+    for key in backend.scan(&key_prefix("summarize")).await {
+        if let Some(value) = backend.get(&key).await {
+            entries.push((key, value));
         }
     }
+
+    entries
 }
 
-pub fn get_robotstxt_cache(domain: &str) -> Option<String> {
-    let mut connection = REDIS_CLIENT.as_ref()?.get_connection().ok()?;
-    let key = gen_key("robotstxt", domain);
-    tracing::debug!("Checking robots.txt cache for key: {}", key);
-    connection.get(&key).ok()
+pub struct SummarizeLock {
+    key: String,
+    token: String,
 }
 
-pub fn set_robotstxt_cache(domain: &str, content: &str) {
-    let mut connection = match REDIS_CLIENT.as_ref().and_then(|c| c.get_connection().ok()) {
-        Some(conn) => conn,
-        None => return,
+/// Takes the lock that keeps other instances from summarizing the same URL at the same time.
+/// Returns `None` if another instance holds it. Without cross-instance coalescing the lock is always granted.
+pub async fn lock_summarize(url: &str, lang: Option<String>, variant: Option<&str>) -> Option<SummarizeLock> {
+    let lock = SummarizeLock {
+        key: format!("{}:lock", summarize_key(url, lang, variant)),
+        token: Uuid::new_v4().to_string(),
     };
 
-    let key = gen_key("robotstxt", domain);
-    tracing::debug!("Setting robots.txt cache for key: {key} ({domain})");
+    if !CONFIG.load().cache.coalesce_across_instances {
+        return Some(lock);
+    }
 
-    match connection.set_ex::<&String, &str, String>(&key, content, 86400) {
-        Ok(_) => (),
-        Err(e) => {
-            tracing::error!("Failed to set robots.txt cache for key {}: {}", key, e);
+    let Some(backend) = BACKEND.as_ref() else { return Some(lock) };
+    let ttl = Duration::from_millis(CONFIG.load().cache.coalesce_lock_ttl);
+
+    if backend.try_lock(&lock.key, &lock.token, ttl).await {
+        Some(lock)
+    } else {
+        None
+    }
+}
+
+pub async fn unlock_summarize(lock: SummarizeLock) {
+    if !CONFIG.load().cache.coalesce_across_instances {
+        return;
+    }
+
+    if let Some(backend) = BACKEND.as_ref() {
+        backend.unlock(&lock.key, &lock.token).await;
+    }
+}
+
+/// Polls the cache until the instance holding the summarize lock has stored its result, or the lock would have expired.
+pub async fn wait_summarize_cache(url: &str, lang: Option<String>, variant: Option<&str>) -> Option<String> {
+    let backend = BACKEND.as_ref()?;
+    let key = summarize_key(url, lang, variant);
+    let deadline = Instant::now() + Duration::from_millis(CONFIG.load().cache.coalesce_lock_ttl);
+
+    // Polls go to the backend directly so that they do not show up as misses in the metrics
+    while Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        if let Some(cached) = backend.get(&key).await {
+            return Some(cached);
         }
     }
+
+    tracing::debug!("Timed out waiting for another instance to summarize {}", url);
+    None
+}
+
+pub async fn get_robotstxt_cache(domain: &str) -> Option<String> {
+    let key = gen_key("robotstxt", domain);
+    tracing::debug!("Checking robots.txt cache for key: {}", key);
+    get("robotstxt", &key).await
+}
+
+pub async fn set_robotstxt_cache(domain: &str, content: &str) {
+    let key = gen_key("robotstxt", domain);
+    tracing::debug!("Setting robots.txt cache for key: {key} ({domain})");
+    set("robotstxt", &key, content, 86400).await;
 }
@@ -0,0 +1,30 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    fn id(&self) -> &str;
+    async fn init(&self) {}
+    async fn get(&self, key: &str) -> Option<String>;
+    async fn set(&self, key: &str, value: &str, ttl: u64);
+    async fn delete(&self, key: &str) -> bool;
+
+    /// Lists the keys starting with `prefix`. This walks the whole keyspace, so it is only meant for administrative use.
+    async fn scan(&self, prefix: &str) -> Vec<String>;
+
+    /// Called once on shutdown after all requests have been drained
+    async fn close(&self) {}
+
+    /// Checks that the backend is reachable, used by the readiness probe
+    async fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Takes a lock that is visible to other instances. Backends local to the process have nothing to coordinate with.
+    async fn try_lock(&self, _key: &str, _token: &str, _ttl: Duration) -> bool {
+        true
+    }
+
+    async fn unlock(&self, _key: &str, _token: &str) {}
+}
@@ -0,0 +1,69 @@
+use crate::core::cache::backend::CacheBackend;
+use async_trait::async_trait;
+use lru::LruCache;
+use std::{
+    num::NonZeroUsize,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+struct Entry {
+    value: String,
+    expires_at: Instant,
+}
+
+pub struct MemoryBackend {
+    entries: Mutex<LruCache<String, Entry>>,
+}
+
+impl MemoryBackend {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryBackend {
+    fn id(&self) -> &str {
+        "memory"
+    }
+
+    async fn get(&self, key: &str) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get(key)?;
+
+        if entry.expires_at <= Instant::now() {
+            entries.pop(key);
+            return None;
+        }
+
+        Some(entry.value.clone())
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: u64) {
+        let entry = Entry {
+            value: value.to_string(),
+            expires_at: Instant::now() + Duration::from_secs(ttl),
+        };
+
+        self.entries.lock().unwrap().put(key.to_string(), entry);
+    }
+
+    async fn delete(&self, key: &str) -> bool {
+        self.entries.lock().unwrap().pop(key).is_some()
+    }
+
+    async fn scan(&self, prefix: &str) -> Vec<String> {
+        let now = Instant::now();
+        let entries = self.entries.lock().unwrap();
+
+        entries
+            .iter()
+            .filter(|(key, entry)| key.starts_with(prefix) && entry.expires_at > now)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{config::ICache, core::cache::backend::CacheBackend};
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use connection::RedisConnection;
+use once_cell::sync::Lazy;
+use redis::{AsyncCommands, RedisResult, Script, aio::ConnectionManagerConfig};
+use std::time::Duration;
+use url::Url;
+
+mod connection;
+
+// Only release the lock if it is still ours, it may have expired and been taken by another instance
+static UNLOCK_SCRIPT: Lazy<Script> =
+    Lazy::new(|| Script::new(r#"if redis.call("GET", KEYS[1]) == ARGV[1] then return redis.call("DEL", KEYS[1]) else return 0 end"#));
+
+pub struct RedisBackend {
+    connection: RedisConnection,
+}
+
+impl RedisBackend {
+    pub fn new(cache_config: &ICache) -> Result<Self> {
+        let connection_url = connection_url(cache_config)?;
+        let connection_timeout = Duration::from_millis(cache_config.connection_timeout);
+        let command_timeout = Duration::from_millis(cache_config.command_timeout);
+
+        // Retries are kept short so that an unavailable server degrades into cache misses instead of stalling requests.
+        let manager_config = ConnectionManagerConfig::new()
+            .set_number_of_retries(1)
+            .set_connection_timeout(Some(connection_timeout))
+            .set_response_timeout(Some(command_timeout));
+
+        let connection = match cache_config.mode.as_str() {
+            "standalone" => RedisConnection::standalone(&connection_url, manager_config)?,
+            "cluster" => {
+                let nodes = if cache_config.nodes.is_empty() {
+                    vec![connection_url]
+                } else {
+                    cache_config.nodes.clone()
+                };
+                RedisConnection::cluster(nodes, connection_timeout, command_timeout)?
+            }
+            "sentinel" => {
+                if cache_config.nodes.is_empty() {
+                    bail!("Sentinel mode requires the sentinel addresses to be listed in cache.nodes");
+                }
+                RedisConnection::sentinel(
+                    cache_config.nodes.clone(),
+                    &cache_config.sentinel_master,
+                    &connection_url,
+                    connection_timeout,
+                    manager_config,
+                )?
+            }
+            other => bail!("Unknown Redis mode '{}'", other),
+        };
+
+        Ok(Self { connection })
+    }
+}
+
+/// Uses `url` as is if set, otherwise assembles one from the individual fields
+fn connection_url(cache_config: &ICache) -> Result<String> {
+    if let Some(url) = &cache_config.url {
+        return Ok(url.clone());
+    }
+
+    let host = if cache_config.host.contains(':') && !cache_config.host.starts_with('[') {
+        format!("[{}]", cache_config.host)
+    } else {
+        cache_config.host.clone()
+    };
+
+    // Setting the credentials through Url percent-encodes them
+    let mut url = Url::parse(&format!("redis://{}:{}", host, cache_config.port))?;
+    if let Some(username) = &cache_config.username {
+        url.set_username(username).map_err(|_| anyhow::anyhow!("Invalid Redis username"))?;
+    }
+    if let Some(password) = &cache_config.password {
+        url.set_password(Some(password)).map_err(|_| anyhow::anyhow!("Invalid Redis password"))?;
+    }
+    if let Some(db) = cache_config.db {
+        url.set_path(&db.to_string());
+    }
+
+    Ok(url.to_string())
+}
+
+#[async_trait]
+impl CacheBackend for RedisBackend {
+    fn id(&self) -> &str {
+        "redis"
+    }
+
+    async fn init(&self) {
+        match self.ping().await {
+            Ok(_) => tracing::info!("Connected to Redis server"),
+            Err(e) => tracing::warn!("Redis server is unavailable, requests will not be cached until it recovers: {}", e),
+        }
+    }
+
+    async fn close(&self) {
+        // Every write is awaited by the request that issued it, so there is nothing left to flush once requests are drained
+        let result: RedisResult<()> = redis::cmd("QUIT").query_async(&mut self.connection.clone()).await;
+        match result {
+            Ok(_) => tracing::info!("Closed Redis connection"),
+            Err(e) => tracing::debug!("Failed to close Redis connection: {}", e),
+        }
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let _: String = redis::cmd("PING").query_async(&mut self.connection.clone()).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Option<String> {
+        let result: RedisResult<Option<String>> = self.connection.clone().get(key).await;
+
+        match result {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::warn!("Failed to get cache for key {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: u64) {
+        let result: RedisResult<()> = self.connection.clone().set_ex(key, value, ttl).await;
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to set cache for key {}: {}", key, e);
+        }
+    }
+
+    async fn delete(&self, key: &str) -> bool {
+        let result: RedisResult<usize> = self.connection.clone().del(key).await;
+
+        match result {
+            Ok(deleted) => deleted > 0,
+            Err(e) => {
+                tracing::warn!("Failed to delete cache for key {}: {}", key, e);
+                false
+            }
+        }
+    }
+
+    async fn scan(&self, prefix: &str) -> Vec<String> {
+        let mut connection = self.connection.clone();
+        let pattern = format!("{prefix}*");
+        let mut keys = Vec::new();
+
+        let nodes = match connection.scan_nodes().await {
+            Ok(nodes) => nodes,
+            Err(e) => {
+                tracing::warn!("Failed to list nodes to scan for prefix {}: {}", prefix, e);
+                return keys;
+            }
+        };
+
+        for node in nodes {
+            let mut cursor = 0u64;
+            loop {
+                match connection.scan(&node, cursor, &pattern).await {
+                    Ok((next, batch)) => {
+                        keys.extend(batch);
+                        if next == 0 {
+                            break;
+                        }
+                        cursor = next;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to scan keys with prefix {}: {}", prefix, e);
+                        break;
+                    }
+                }
+            }
+        }
+
+        keys
+    }
+
+    async fn try_lock(&self, key: &str, token: &str, ttl: Duration) -> bool {
+        let result: RedisResult<Option<String>> = redis::cmd("SET")
+            .arg(key)
+            .arg(token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl.as_millis() as u64)
+            .query_async(&mut self.connection.clone())
+            .await;
+
+        match result {
+            Ok(value) => value.is_some(),
+            Err(e) => {
+                // Fall back to fetching without coordination rather than waiting on a lock nobody holds
+                tracing::warn!("Failed to acquire lock {}: {}", key, e);
+                true
+            }
+        }
+    }
+
+    async fn unlock(&self, key: &str, token: &str) {
+        let result: RedisResult<i64> = UNLOCK_SCRIPT.key(key).arg(token).invoke_async(&mut self.connection.clone()).await;
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to release lock {}: {}", key, e);
+        }
+    }
+}
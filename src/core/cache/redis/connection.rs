@@ -0,0 +1,278 @@
+use futures::{FutureExt, future::BoxFuture};
+use redis::{
+    Client, Cmd, ConnectionAddr, ErrorKind, IntoConnectionInfo, Pipeline, RedisError, RedisFuture, RedisResult, ServerErrorKind, TlsMode, Value,
+    aio::{ConnectionLike, ConnectionManager, ConnectionManagerConfig},
+    cluster::ClusterClient,
+    cluster_async::ClusterConnection,
+    cluster_routing::{RoutingInfo, SingleNodeRoutingInfo},
+    sentinel::{SentinelClient, SentinelNodeConnectionInfo, SentinelServerType},
+};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// Delay before connecting again after an attempt failed, so that requests do not each wait for the connection timeout
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+type Connect<C> = Arc<dyn Fn() -> BoxFuture<'static, RedisResult<C>> + Send + Sync>;
+
+/// Handle to the Redis deployment, cheap to clone like the connections it wraps.
+/// Implements `ConnectionLike` so that commands are issued the same way regardless of the topology.
+#[derive(Clone)]
+pub enum RedisConnection {
+    Standalone(ConnectionManager),
+    Cluster(LazyConnection<ClusterConnection>),
+    Sentinel(LazyConnection<ConnectionManager>),
+}
+
+impl RedisConnection {
+    /// The manager connects on first use and reconnects whenever the connection drops
+    pub fn standalone(url: &str, manager_config: ConnectionManagerConfig) -> RedisResult<Self> {
+        let client = Client::open(url)?;
+        Ok(Self::Standalone(ConnectionManager::new_lazy_with_config(client, manager_config)?))
+    }
+
+    /// Credentials and TLS are taken from the node URLs, the cluster connection follows topology changes by itself
+    pub fn cluster(nodes: Vec<String>, connection_timeout: Duration, command_timeout: Duration) -> RedisResult<Self> {
+        let client = ClusterClient::builder(nodes)
+            .retries(1)
+            .connection_timeout(connection_timeout)
+            .response_timeout(command_timeout)
+            .build()?;
+
+        let connect: Connect<ClusterConnection> = Arc::new(move || {
+            let client = client.clone();
+            async move { client.get_async_connection().await }.boxed()
+        });
+
+        Ok(Self::Cluster(LazyConnection::new(connect, connection_timeout, false)))
+    }
+
+    /// Asks the sentinels for the current master, and asks again once the master stops accepting writes or becomes unreachable.
+    /// Credentials, db and TLS for the master are taken from `url`, its host is not used.
+    pub fn sentinel(
+        sentinels: Vec<String>,
+        master_name: &str,
+        url: &str,
+        connection_timeout: Duration,
+        manager_config: ConnectionManagerConfig,
+    ) -> RedisResult<Self> {
+        let master_info = url.into_connection_info()?;
+        let mut node_info = SentinelNodeConnectionInfo::default().set_redis_connection_info(master_info.redis_settings().clone());
+        if let ConnectionAddr::TcpTls { insecure, .. } = master_info.addr() {
+            node_info = node_info.set_tls_mode(if *insecure { TlsMode::Insecure } else { TlsMode::Secure });
+        }
+
+        let client = SentinelClient::build(sentinels, master_name.to_string(), Some(node_info), SentinelServerType::Master)?;
+        let client = Arc::new(tokio::sync::Mutex::new(client));
+
+        let connect: Connect<ConnectionManager> = Arc::new(move || {
+            let client = client.clone();
+            let manager_config = manager_config.clone();
+            async move {
+                let master = client.lock().await.async_get_client().await?;
+                ConnectionManager::new_with_config(master, manager_config).await
+            }
+            .boxed()
+        });
+
+        Ok(Self::Sentinel(LazyConnection::new(connect, connection_timeout, true)))
+    }
+
+    /// Nodes that each have to be scanned to walk the whole keyspace, `None` stands for the only node there is
+    pub async fn scan_nodes(&mut self) -> RedisResult<Vec<Option<(String, u16)>>> {
+        let Self::Cluster(cluster) = self else {
+            return Ok(vec![None]);
+        };
+
+        // Every slot range lists its primary as the first node, as [host, port, id, ...]
+        let slots: Vec<Vec<Value>> = redis::cmd("CLUSTER").arg("SLOTS").query_async(cluster).await?;
+        let mut nodes = Vec::new();
+        for range in slots {
+            let Some(Value::Array(primary)) = range.get(2) else { continue };
+            let (Some(host), Some(port)) = (primary.first(), primary.get(1)) else {
+                continue;
+            };
+
+            let node = Some((redis::from_redis_value_ref::<String>(host)?, redis::from_redis_value_ref::<u16>(port)?));
+            if !nodes.contains(&node) {
+                nodes.push(node);
+            }
+        }
+
+        Ok(nodes)
+    }
+
+    /// Runs a single SCAN iteration on `node`, as returned by `scan_nodes`
+    pub async fn scan(&mut self, node: &Option<(String, u16)>, cursor: u64, pattern: &str) -> RedisResult<(u64, Vec<String>)> {
+        let mut cmd = redis::cmd("SCAN");
+        cmd.arg(cursor).arg("MATCH").arg(pattern).arg("COUNT").arg(1000);
+
+        match (self, node) {
+            (Self::Cluster(cluster), Some((host, port))) => {
+                let routing = RoutingInfo::SingleNode(SingleNodeRoutingInfo::ByAddress {
+                    host: host.clone(),
+                    port: *port,
+                });
+                let value = cluster.get().await?.route_command(cmd, routing).await?;
+                Ok(redis::from_redis_value(value)?)
+            }
+            (connection, _) => cmd.query_async(connection).await,
+        }
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            Self::Standalone(connection) => connection.req_packed_command(cmd),
+            Self::Cluster(connection) => connection.req_packed_command(cmd),
+            Self::Sentinel(connection) => connection.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(&'a mut self, pipeline: &'a Pipeline, offset: usize, count: usize) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            Self::Standalone(connection) => connection.req_packed_commands(pipeline, offset, count),
+            Self::Cluster(connection) => connection.req_packed_commands(pipeline, offset, count),
+            Self::Sentinel(connection) => connection.req_packed_commands(pipeline, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            Self::Standalone(connection) => connection.get_db(),
+            Self::Cluster(connection) => connection.get_db(),
+            Self::Sentinel(connection) => connection.get_db(),
+        }
+    }
+}
+
+struct LazyState<C> {
+    connection: Option<C>,
+    retry_at: Option<Instant>,
+}
+
+/// A connection that needs a discovery step before it can be established, which is only done on first use so that
+/// an unavailable deployment degrades into cache misses instead of failing startup.
+/// While an attempt is in progress or backing off, commands fail right away rather than queueing up behind it.
+pub struct LazyConnection<C> {
+    connect: Connect<C>,
+    timeout: Duration,
+    // Whether to start over from discovery when the connection fails, for topologies where the node we talk to can change
+    rediscover: bool,
+    state: Arc<Mutex<LazyState<C>>>,
+    connecting: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl<C> Clone for LazyConnection<C> {
+    fn clone(&self) -> Self {
+        Self {
+            connect: self.connect.clone(),
+            timeout: self.timeout,
+            rediscover: self.rediscover,
+            state: self.state.clone(),
+            connecting: self.connecting.clone(),
+        }
+    }
+}
+
+impl<C: ConnectionLike + Clone + Send + 'static> LazyConnection<C> {
+    fn new(connect: Connect<C>, timeout: Duration, rediscover: bool) -> Self {
+        Self {
+            connect,
+            timeout,
+            rediscover,
+            state: Arc::new(Mutex::new(LazyState {
+                connection: None,
+                retry_at: None,
+            })),
+            connecting: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    async fn get(&self) -> RedisResult<C> {
+        if let Some(connection) = &self.state.lock().unwrap().connection {
+            return Ok(connection.clone());
+        }
+
+        let Ok(_connecting) = self.connecting.try_lock() else {
+            return Err(not_connected());
+        };
+
+        {
+            let state = self.state.lock().unwrap();
+            if let Some(connection) = &state.connection {
+                return Ok(connection.clone());
+            }
+            if state.retry_at.is_some_and(|at| at > Instant::now()) {
+                return Err(not_connected());
+            }
+        }
+
+        let result = match tokio::time::timeout(self.timeout, (self.connect)()).await {
+            Ok(result) => result,
+            Err(_) => Err(io::Error::from(io::ErrorKind::TimedOut).into()),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        match result {
+            Ok(connection) => {
+                state.connection = Some(connection.clone());
+                state.retry_at = None;
+                Ok(connection)
+            }
+            Err(e) => {
+                state.retry_at = Some(Instant::now() + RECONNECT_DELAY);
+                Err(e)
+            }
+        }
+    }
+
+    fn discard_on(&self, error: &RedisError) {
+        // A former master that was demoted by a failover rejects writes with READONLY
+        let topology_changed = error.is_io_error() ||
+            error.is_connection_dropped() ||
+            error.is_connection_refusal() ||
+            error.kind() == ErrorKind::Server(ServerErrorKind::ReadOnly);
+
+        if self.rediscover && topology_changed {
+            tracing::debug!("Redis connection failed, discovering the master again: {}", error);
+            self.state.lock().unwrap().connection = None;
+        }
+    }
+}
+
+impl<C: ConnectionLike + Clone + Send + 'static> ConnectionLike for LazyConnection<C> {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        async move {
+            let result = self.get().await?.req_packed_command(cmd).await;
+            if let Err(e) = &result {
+                self.discard_on(e);
+            }
+            result
+        }
+        .boxed()
+    }
+
+    fn req_packed_commands<'a>(&'a mut self, pipeline: &'a Pipeline, offset: usize, count: usize) -> RedisFuture<'a, Vec<Value>> {
+        async move {
+            let result = self.get().await?.req_packed_commands(pipeline, offset, count).await;
+            if let Err(e) = &result {
+                self.discard_on(e);
+            }
+            result
+        }
+        .boxed()
+    }
+
+    fn get_db(&self) -> i64 {
+        self.state.lock().unwrap().connection.as_ref().map_or(0, ConnectionLike::get_db)
+    }
+}
+
+fn not_connected() -> RedisError {
+    RedisError::from((ErrorKind::Io, "Connection to Redis is not established"))
+}
@@ -0,0 +1,72 @@
+use crate::core::cache::{backend::CacheBackend, memory::MemoryBackend, redis::RedisBackend};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Serves hot entries from process memory and falls back to Redis, which is shared between instances.
+pub struct TieredBackend {
+    memory: MemoryBackend,
+    redis: RedisBackend,
+    /// Upper bound for the lifetime of an entry in the memory tier, so that changes made through Redis propagate
+    memory_ttl: u64,
+}
+
+impl TieredBackend {
+    pub fn new(memory: MemoryBackend, redis: RedisBackend, memory_ttl: u64) -> Self {
+        Self { memory, redis, memory_ttl }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for TieredBackend {
+    fn id(&self) -> &str {
+        "tiered"
+    }
+
+    async fn init(&self) {
+        self.redis.init().await;
+    }
+
+    async fn get(&self, key: &str) -> Option<String> {
+        if let Some(value) = self.memory.get(key).await {
+            return Some(value);
+        }
+
+        let value = self.redis.get(key).await?;
+        self.memory.set(key, &value, self.memory_ttl).await;
+        Some(value)
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: u64) {
+        tokio::join!(self.memory.set(key, value, ttl.min(self.memory_ttl)), self.redis.set(key, value, ttl));
+    }
+
+    async fn close(&self) {
+        self.redis.close().await;
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.redis.ping().await
+    }
+
+    async fn delete(&self, key: &str) -> bool {
+        let (memory, redis) = tokio::join!(self.memory.delete(key), self.redis.delete(key));
+        memory || redis
+    }
+
+    async fn scan(&self, prefix: &str) -> Vec<String> {
+        let (mut keys, redis_keys) = tokio::join!(self.memory.scan(prefix), self.redis.scan(prefix));
+        keys.extend(redis_keys);
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    async fn try_lock(&self, key: &str, token: &str, ttl: Duration) -> bool {
+        self.redis.try_lock(key, token, ttl).await
+    }
+
+    async fn unlock(&self, key: &str, token: &str) {
+        self.redis.unlock(key, token).await;
+    }
+}
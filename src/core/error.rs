@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Reasons a summarization can fail. Serialized with the code as tag so that failures can be cached and restored.
+#[derive(Error, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "code", content = "detail", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SummaryError {
+    #[error("Invalid language code: {0}")]
+    InvalidLanguage(String),
+    #[error("The URL is not supported by this handler")]
+    UnsupportedUrl,
+    #[error("Scraping is disallowed by robots.txt")]
+    RobotsDisallowed,
+    #[error("The destination is blocked by the access control list")]
+    AclBlocked,
+    #[error("The destination is denied by the security policy")]
+    TargetDenied,
+    #[error("Timed out while fetching the URL")]
+    Timeout,
+    #[error("Exceeded the limit of {0} redirects")]
+    TooManyRedirects(u32),
+    #[error("Redirected back to a URL that was already visited")]
+    RedirectLoop,
+    #[error("Response body exceeds the content length limit of {0} bytes")]
+    BodyTooLarge(usize),
+    #[error("Response has no Content-Length header")]
+    ContentLengthRequired,
+    #[error("Unsupported content type: {0}")]
+    UnsupportedContentType(String),
+    #[error("Upstream server responded with status {0}")]
+    UpstreamStatus(u16),
+    #[error("Failed to fetch the URL: {0}")]
+    NetworkError(String),
+    #[error("No title found")]
+    NoTitle,
+    #[error("Invalid response from upstream: {0}")]
+    InvalidResponse(String),
+    /// Answer to a conditional refresh, the cached summary is still valid
+    #[error("Upstream content has not been modified")]
+    NotModified,
+}
+
+impl SummaryError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SummaryError::InvalidLanguage(_) => "INVALID_LANGUAGE",
+            SummaryError::UnsupportedUrl => "UNSUPPORTED_URL",
+            SummaryError::RobotsDisallowed => "ROBOTS_DISALLOWED",
+            SummaryError::AclBlocked => "ACL_BLOCKED",
+            SummaryError::TargetDenied => "TARGET_DENIED",
+            SummaryError::Timeout => "TIMEOUT",
+            SummaryError::TooManyRedirects(_) => "TOO_MANY_REDIRECTS",
+            SummaryError::RedirectLoop => "REDIRECT_LOOP",
+            SummaryError::BodyTooLarge(_) => "BODY_TOO_LARGE",
+            SummaryError::ContentLengthRequired => "CONTENT_LENGTH_REQUIRED",
+            SummaryError::UnsupportedContentType(_) => "UNSUPPORTED_CONTENT_TYPE",
+            SummaryError::UpstreamStatus(_) => "UPSTREAM_STATUS",
+            SummaryError::NetworkError(_) => "NETWORK_ERROR",
+            SummaryError::NoTitle => "NO_TITLE",
+            SummaryError::InvalidResponse(_) => "INVALID_RESPONSE",
+            SummaryError::NotModified => "NOT_MODIFIED",
+        }
+    }
+
+    /// Transient failures are likely to go away on retry, so they are cached for a shorter time than permanent ones.
+    /// A response that cannot be parsed comes out the same on every retry, unlike a connection that failed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SummaryError::Timeout | SummaryError::NetworkError(_) => true,
+            SummaryError::UpstreamStatus(status) => *status >= 500 || *status == 429,
+            _ => false,
+        }
+    }
+
+    pub fn invalid_response(err: impl std::fmt::Display) -> Self {
+        SummaryError::InvalidResponse(err.to_string())
+    }
+}
@@ -0,0 +1,80 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramVec, IntCounter, IntCounterVec, TextEncoder, register_histogram_vec, register_int_counter, register_int_counter_vec,
+};
+
+pub static HTTP_REQUESTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "nokogiri_http_requests_total",
+        "Number of HTTP requests handled, by route and status",
+        &["route", "method", "status"]
+    )
+    .unwrap()
+});
+
+pub static HTTP_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "nokogiri_http_request_duration_seconds",
+        "Time taken to handle HTTP requests, by route and status",
+        &["route", "method", "status"]
+    )
+    .unwrap()
+});
+
+pub static SUMMARIZE_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "nokogiri_summarize_total",
+        "Number of summarizations run by each handler, by outcome (\"success\" or the error code)",
+        &["handler", "outcome"]
+    )
+    .unwrap()
+});
+
+pub static CACHE_OPERATIONS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "nokogiri_cache_operations_total",
+        "Number of cache lookups and writes, by category and operation (hit, miss, set)",
+        &["category", "operation"]
+    )
+    .unwrap()
+});
+
+pub static UPSTREAM_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "nokogiri_upstream_request_duration_seconds",
+        "Time until response headers are received from upstream, by outcome",
+        &["outcome"]
+    )
+    .unwrap()
+});
+
+pub static UPSTREAM_ERRORS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "nokogiri_upstream_errors_total",
+        "Number of failed upstream fetches, by error code",
+        &["code"]
+    )
+    .unwrap()
+});
+
+pub static ROBOTS_DECISIONS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "nokogiri_robots_decisions_total",
+        "Number of robots.txt checks, by decision (allow, deny)",
+        &["decision"]
+    )
+    .unwrap()
+});
+
+pub static DOWNLOADED_BYTES_TOTAL: Lazy<IntCounter> =
+    Lazy::new(|| register_int_counter!("nokogiri_downloaded_bytes_total", "Number of response body bytes received from upstream").unwrap());
+
+/// Renders every registered metric in the Prometheus text exposition format
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        tracing::error!("Failed to encode metrics: {}", e);
+    }
+
+    String::from_utf8(buffer).unwrap_or_default()
+}
@@ -1,50 +1,205 @@
-use crate::{config::CONFIG, core::summary::def::SummarizeArguments};
-use anyhow::Result;
+use crate::{
+    config::{AppConfig, CONFIG},
+    core::{error::SummaryError, metrics, request::pattern::HostPattern, summary::def::SummarizeArguments},
+};
+use arc_swap::ArcSwap;
 use http_acl_reqwest::{HttpAcl, HttpAclMiddleware};
-use hyper_util::client::legacy::Error as HyperUtilError;
 use once_cell::sync::Lazy;
 use parse_size::parse_size;
-use reqwest::{Client, Response, cookie::Jar, header::HeaderMap, redirect::Policy};
+use reqwest::{
+    Client, Method, Response, StatusCode,
+    cookie::Jar,
+    dns::{Name, Resolve},
+    header::{
+        AUTHORIZATION, CONTENT_DISPOSITION, COOKIE, ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LOCATION, PROXY_AUTHORIZATION,
+    },
+    redirect::Policy,
+};
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Error as ReqwestMiddlewareError};
-use std::{env, error::Error, fmt, sync::Arc, time::Duration};
-use url::Url;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    error::Error,
+    fmt,
+    net::IpAddr,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+use url::{Host, Url};
+use urlencoding::decode;
 
+mod charset;
+pub mod content;
+mod freshness;
+pub mod pattern;
+pub mod policy;
+pub mod proxy;
 mod resolver;
 pub mod robotstxt;
 
+// Enough of the body to recognize a file by its signature, the resource header of the MIME Sniffing Standard
+const SNIFF_LEN: usize = 1445;
+
 pub static COOKIE_JAR: Lazy<Arc<Jar>> = Lazy::new(|| Arc::new(Jar::default()));
 
-pub static CLIENT: Lazy<ClientWithMiddleware> = Lazy::new(|| {
-    let acl = HttpAcl::builder()
-        .ip_acl_default(true)
-        .port_acl_default(true)
-        .host_acl_default(true)
-        .non_global_ip_ranges(CONFIG.security.block_non_global_ips)
-        .build();
-
-    let middleware = HttpAclMiddleware::new(acl);
-    let response_timeout = Duration::from_millis(CONFIG.general.response_timeout);
-    let client = Client::builder()
+// Swapped for a client built from the new settings when the config is reloaded, requests in flight keep the client they started with.
+// Redirects are followed by `follow` rather than by reqwest, so that every hop can be checked before it is requested.
+pub static CLIENT: Lazy<ArcSwap<HttpClient>> = Lazy::new(|| ArcSwap::from_pointee(build_client(&CONFIG.load())));
+
+/// Rebuilds the client, which bakes in the ACL, the proxies and the timeouts
+pub fn reload(config: &AppConfig) {
+    CLIENT.store(Arc::new(build_client(config)));
+}
+
+pub struct HttpClient {
+    client: ClientWithMiddleware,
+    acl: Arc<HttpAcl>,
+    policy: Arc<policy::TargetPolicy>,
+    proxies: Arc<proxy::ProxyRoutes>,
+    resolver: Arc<resolver::CustomDnsResolver>,
+    pinned: Arc<resolver::PinnedHosts>,
+}
+
+impl HttpClient {
+    /// Applies the allowed and denied destinations of `[security]` before a URL is requested.
+    /// A proxy resolves the destination by itself, out of reach of the ACL in the resolver, so the addresses are checked here as well,
+    /// and so are the addresses of a proxy that is requested directly. Addresses in the URL are checked by the middleware like for direct requests.
+    async fn check_target(&self, url: &Url) -> Result<(), SummaryError> {
+        let Some(host) = url.host() else {
+            return Ok(());
+        };
+
+        let host_name = host.to_string();
+        if self.policy.allows_host(&host_name) {
+            return Ok(());
+        }
+        if self.policy.denies_url(url) {
+            tracing::info!("'{}' is denied by the security policy", url);
+            return Err(SummaryError::TargetDenied);
+        }
+
+        let proxied = self.proxies.route(url).is_some();
+        // A proxy is connected to at its pinned addresses without the ACL, which has to be applied here when it is the destination
+        let pinned = matches!(host, Host::Domain(domain) if !proxied && self.pinned.contains(domain));
+        let ips = match host {
+            Host::Ipv4(ip) => vec![IpAddr::V4(ip)],
+            Host::Ipv6(ip) => vec![IpAddr::V6(ip)],
+            Host::Domain(domain) if pinned => match self.pinned.lookup(domain).await {
+                Ok(addrs) => addrs.iter().map(|addr| addr.ip()).collect(),
+                Err(e) => {
+                    tracing::info!("Failed to resolve '{}': {}", domain, e);
+                    return Err(SummaryError::NetworkError(e.to_string()));
+                }
+            },
+            Host::Domain(domain) if proxied || self.policy.denies_ips() => match self.lookup(domain).await {
+                Ok(ips) => ips,
+                Err(e) if proxied => {
+                    tracing::info!("Failed to resolve '{}' before sending it through a proxy: {}", domain, e);
+                    return Err(SummaryError::NetworkError(e));
+                }
+                // The request fails on its own when the name cannot be resolved
+                Err(_) => Vec::new(),
+            },
+            Host::Domain(_) => Vec::new(),
+        };
+
+        // The destination may be reached at any of them
+        for ip in ips {
+            if self.policy.denies_ip(&ip) {
+                tracing::info!("'{}' resolves to {}, which is denied by the security policy", host_name, ip);
+                return Err(SummaryError::TargetDenied);
+            }
+            if (proxied || pinned) && self.acl.is_ip_allowed(&ip).is_denied() {
+                tracing::info!("'{}' resolves to {}, which is blocked by ACL", host_name, ip);
+                return Err(SummaryError::AclBlocked);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn lookup(&self, domain: &str) -> Result<Vec<IpAddr>, String> {
+        let name = Name::from_str(domain).map_err(|e| e.to_string())?;
+        let addrs = self.resolver.resolve(name).await.map_err(|e| e.to_string())?;
+        Ok(addrs.map(|addr| addr.ip()).collect())
+    }
+}
+
+/// Whether `[security]` denies the URL without looking up its addresses, which is cheap enough to check before the cache
+pub fn is_denied_target(url: &Url) -> bool {
+    CLIENT.load().policy.denies_url(url)
+}
+
+/// The lists are validated on startup and on reload, so the fallback is never used in practice
+fn build_acl(config: &AppConfig) -> HttpAcl {
+    policy::build_acl(&config.security).unwrap_or_else(|e| {
+        tracing::error!("Invalid access control lists in config: {}. Using the default ACL.", e);
+        policy::default_acl(&config.security)
+    })
+}
+
+fn build_client(config: &AppConfig) -> HttpClient {
+    // The middleware checks the URL, the resolver the addresses a name resolves to
+    let acl = Arc::new(build_acl(config));
+    let middleware = HttpAclMiddleware::new(build_acl(config));
+    let policy = Arc::new(policy::TargetPolicy::new(&config.security));
+    let proxies = Arc::new(proxy::ProxyRoutes::new(&config.proxy));
+    let resolver = Arc::new(resolver::CustomDnsResolver::default());
+
+    let pinned = Arc::new(resolver::PinnedHosts::new(proxies.hosts(), resolver.clone()));
+    let dns_resolver = resolver::TrustedHostsResolver::new(
+        Arc::new(resolver::AclResolver::new(acl.clone(), resolver.clone())),
+        resolver.clone(),
+        pinned.clone(),
+        HostPattern::parse_all(&config.security.allowed_hosts),
+    );
+
+    let response_timeout = Duration::from_millis(config.general.response_timeout);
+    let mut builder = Client::builder()
         .user_agent(UserAgentList::Default.to_string())
-        .redirect(Policy::limited(CONFIG.general.max_redirect_hops as usize))
-        .timeout(Duration::from_millis(CONFIG.general.operation_timeout))
+        .redirect(Policy::none())
+        .timeout(Duration::from_millis(config.general.operation_timeout))
         .read_timeout(response_timeout)
         .connect_timeout(response_timeout)
         .cookie_provider(Arc::clone(&COOKIE_JAR))
-        .dns_resolver(middleware.with_dns_resolver(Arc::new(resolver::CustomDnsResolver::default())))
-        .build()
-        .unwrap();
+        .dns_resolver(Arc::new(dns_resolver));
+
+    // Without any proxy configured, the proxy environment variables keep working as before
+    if !proxies.is_empty() {
+        builder = builder.proxy(proxy::proxy(proxies.clone()));
+    }
 
-    ClientBuilder::new(client).with(middleware).build()
-});
+    HttpClient {
+        acl,
+        client: ClientBuilder::new(builder.build().unwrap()).with(middleware).build(),
+        policy,
+        proxies,
+        resolver,
+        pinned,
+    }
+}
 
-pub static CONTENT_LENGTH_LIMIT: Lazy<usize> = Lazy::new(|| match parse_size(&CONFIG.general.content_length_limit) {
-    Ok(size) => size as usize,
-    Err(e) => {
-        tracing::error!("Invalid content length limit in config: {}. Using default 10 MB.", e);
-        10 * 1024 * 1024
+/// The configured limits are validated on startup and on reload, so the fallbacks are never used in practice
+pub fn content_length_limit() -> usize {
+    match parse_size(&CONFIG.load().general.content_length_limit) {
+        Ok(size) => size as usize,
+        Err(e) => {
+            tracing::error!("Invalid content length limit in config: {}. Using default 10 MB.", e);
+            10 * 1024 * 1024
+        }
     }
-});
+}
+
+fn head_read_limit() -> usize {
+    match parse_size(&CONFIG.load().general.head_read_limit) {
+        Ok(size) => size as usize,
+        Err(e) => {
+            tracing::error!("Invalid head read limit in config: {}. Using default 2 MB.", e);
+            2 * 1024 * 1024
+        }
+    }
+}
 
 #[derive(Debug, Clone, Default, PartialEq)]
 pub enum UserAgentList {
@@ -73,6 +228,13 @@ impl fmt::Display for UserAgentList {
     }
 }
 
+/// Validators of a previously fetched response, sent back to make a conditional request
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
 #[derive(Debug, Default)]
 pub struct RequestOptions {
     pub user_agent: UserAgentList,
@@ -80,84 +242,269 @@ pub struct RequestOptions {
     pub headers: Option<HeaderMap>,
     pub lang: Option<String>,
     pub user_agent_string: Option<String>,
-    // pub follow_redirects: Option<bool>,
-    // pub response_timeout: Option<u64>,
-    // pub operation_timeout: Option<u64>,
-    // pub content_length_limit: Option<usize>,
-    // pub content_length_required: Option<bool>,
+    pub follow_redirects: Option<bool>,
+    pub response_timeout: Option<u64>,
+    pub operation_timeout: Option<u64>,
+    pub content_length_limit: Option<usize>,
+    pub content_length_required: Option<bool>,
+    pub validators: Option<Validators>,
+    /// Check redirect targets against robots.txt, for callers that checked the requested URL
+    pub check_robots_txt: bool,
 }
 
+/// A redirect that was followed on the way to the final response
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub url: String,
+    pub status: u16,
+    pub location: String,
+}
+
+// Validators are not copied, handlers opt in for the request the validators were taken from
 impl From<&SummarizeArguments> for RequestOptions {
     fn from(args: &SummarizeArguments) -> Self {
         RequestOptions {
             lang: args.lang.clone(),
             user_agent_string: args.user_agent.clone(),
-            // follow_redirects: args.follow_redirects,
-            // response_timeout: args.response_timeout,
-            // operation_timeout: args.operation_timeout,
-            // content_length_limit: args.content_length_limit,
-            // content_length_required: args.content_length_required,
+            follow_redirects: args.follow_redirects,
+            response_timeout: args.response_timeout,
+            operation_timeout: args.operation_timeout,
+            content_length_limit: args.content_length_limit,
+            content_length_required: args.content_length_required,
             ..Default::default()
         }
     }
 }
 
+impl RequestOptions {
+    /// Requested values are only allowed to tighten the limits configured in `[general]`
+    fn response_timeout(&self) -> Duration {
+        let limit = CONFIG.load().general.response_timeout;
+        Duration::from_millis(self.response_timeout.filter(|x| *x > 0).map_or(limit, |x| x.min(limit)))
+    }
+
+    fn operation_timeout(&self) -> Duration {
+        let limit = CONFIG.load().general.operation_timeout;
+        Duration::from_millis(self.operation_timeout.filter(|x| *x > 0).map_or(limit, |x| x.min(limit)))
+    }
+
+    fn content_length_limit(&self) -> usize {
+        match (self.content_length_limit.filter(|x| *x > 0), content_length_limit()) {
+            (Some(requested), 0) => requested,
+            (Some(requested), limit) => requested.min(limit),
+            (None, limit) => limit,
+        }
+    }
+
+    /// The effective options that make the request differ from one made with the `[general]` defaults, `None` if
+    /// there are none. Results fetched with them are cached and coalesced separately.
+    pub fn variant(&self) -> Option<String> {
+        let config = CONFIG.load();
+        let mut parts = Vec::new();
+
+        if let Some(ua) = self.user_agent_string.as_deref().filter(|ua| *ua != UserAgentList::Default.to_string()) {
+            parts.push(format!("ua={}", ua));
+        }
+        if self.follow_redirects == Some(false) {
+            parts.push("redirects=0".to_string());
+        }
+        let response_timeout = self.response_timeout().as_millis();
+        if response_timeout != u128::from(config.general.response_timeout) {
+            parts.push(format!("response_timeout={}", response_timeout));
+        }
+        let operation_timeout = self.operation_timeout().as_millis();
+        if operation_timeout != u128::from(config.general.operation_timeout) {
+            parts.push(format!("operation_timeout={}", operation_timeout));
+        }
+        let content_length_limit = self.content_length_limit();
+        if content_length_limit != self::content_length_limit() {
+            parts.push(format!("content_length_limit={}", content_length_limit));
+        }
+        if self.content_length_required == Some(true) {
+            parts.push("content_length_required".to_string());
+        }
+
+        (!parts.is_empty()).then(|| parts.join(";"))
+    }
+}
+
 #[derive(Debug)]
 pub struct ResponseWrapper {
     pub response: Response,
+    /// Redirects that led to `response`, in the order they were followed
+    pub redirects: Vec<Redirect>,
+    content_length_limit: usize,
+    response_timeout: Duration,
+    // Taken before reading the body, reqwest only reports how much of it is left
+    content_length: Option<u64>,
+    // Start of the body that was read ahead by `peek`, handed out again by `bytes`
+    head: Vec<u8>,
+    received_size: usize,
 }
 
 impl ResponseWrapper {
     pub fn new(response: Response) -> Self {
-        Self { response }
+        Self {
+            content_length: response.content_length(),
+            response,
+            redirects: Vec::new(),
+            content_length_limit: content_length_limit(),
+            response_timeout: Duration::from_millis(CONFIG.load().general.response_timeout),
+            head: Vec::new(),
+            received_size: 0,
+        }
     }
 
-    pub async fn text(mut self) -> Option<String> {
-        if *CONTENT_LENGTH_LIMIT == 0 {
-            tracing::debug!("Content length limit is disabled, reading entire response body");
-            return self.response.text().await.ok();
-        }
+    /// URL of the final response, after following redirects
+    pub fn url(&self) -> &Url {
+        self.response.url()
+    }
 
-        let mut received_bytes = Vec::new();
-        let mut received_size = 0;
+    pub async fn text(self) -> Result<String, SummaryError> {
+        let content_type = self.content_type();
+        let tld = self.tld();
+        let received_bytes = self.bytes().await?;
 
-        while let Some(chunk) = self.response.chunk().await.transpose() {
-            let chunk = match chunk {
-                Ok(c) => c,
-                Err(e) => {
-                    tracing::error!("Failed to read chunk from response body: {}", e);
-                    return None;
-                }
-            };
+        Ok(charset::decode(&received_bytes, content_type.as_deref(), tld.as_deref()))
+    }
 
-            received_size += chunk.len();
-            if received_size > *CONTENT_LENGTH_LIMIT {
-                tracing::warn!("Response body exceeded the content length limit of {:?} bytes", *CONTENT_LENGTH_LIMIT);
-                return None;
+    /// Reads the page only up to the end of `<head>`, where its metadata is, and decodes what was read.
+    /// The rest of a page without a head end in sight is skipped after `general.head_read_limit` bytes.
+    pub async fn head_text(mut self) -> Result<String, SummaryError> {
+        let budget = match (head_read_limit(), self.content_length_limit) {
+            (0, _) => return self.text().await,
+            (budget, 0) => budget,
+            (budget, limit) => budget.min(limit),
+        };
+
+        let content_type = self.content_type();
+        let tld = self.tld();
+        let mut received_bytes = std::mem::take(&mut self.head);
+        let mut searched = 0;
+
+        while !head_received(&received_bytes, &mut searched, budget) {
+            if !self.read_chunk(&mut received_bytes).await? {
+                break;
             }
-            received_bytes.extend_from_slice(&chunk);
         }
 
         tracing::debug!("Received {} bytes", received_bytes.len());
-        String::from_utf8(received_bytes).ok()
+        Ok(charset::decode(&received_bytes, content_type.as_deref(), tld.as_deref()))
     }
 
-    pub fn ttl(&self) -> u64 {
-        self.response
-            .headers()
-            .get("Cache-Control")
-            .and_then(|v| v.to_str().ok())
-            .and_then(|s| {
-                s.split(',').find_map(|part| {
-                    let part = part.trim();
-                    if let Some(age_str) = part.strip_prefix("max-age=") {
-                        age_str.parse::<u64>().ok()
-                    } else {
-                        None
-                    }
-                })
-            })
-            .unwrap_or(300)
+    /// Reads the whole body. A body that is declared to be over the limit is rejected up front, its start can still be read with `peek`.
+    pub async fn bytes(mut self) -> Result<Vec<u8>, SummaryError> {
+        if let Some(length) = self.content_length() &&
+            self.content_length_limit != 0 &&
+            length as usize > self.content_length_limit
+        {
+            tracing::info!(
+                "Content-Length of '{}' ({} bytes) exceeds the limit of {} bytes",
+                self.url(),
+                length,
+                self.content_length_limit
+            );
+            return Err(SummaryError::BodyTooLarge(self.content_length_limit));
+        }
+
+        let mut received_bytes = std::mem::take(&mut self.head);
+        while self.read_chunk(&mut received_bytes).await? {}
+
+        tracing::debug!("Received {} bytes", received_bytes.len());
+        Ok(received_bytes)
+    }
+
+    /// Reads at least `len` bytes from the start of the body, or all of it if it is shorter, without consuming them
+    pub async fn peek(&mut self, len: usize) -> Result<&[u8], SummaryError> {
+        let mut head = std::mem::take(&mut self.head);
+        while head.len() < len && self.read_chunk(&mut head).await? {}
+
+        self.head = head;
+        Ok(&self.head)
+    }
+
+    /// Tells what the body holds from its first bytes and the Content-Type header
+    pub async fn content(&mut self) -> Result<content::Content, SummaryError> {
+        let content_type = self.content_type();
+        let head = self.peek(SNIFF_LEN).await?;
+        Ok(content::detect(content_type.as_deref(), head))
+    }
+
+    // Appends the next chunk of the body to `buf`, returns false once the body is complete
+    async fn read_chunk(&mut self, buf: &mut Vec<u8>) -> Result<bool, SummaryError> {
+        let chunk = match tokio::time::timeout(self.response_timeout, self.response.chunk()).await {
+            Ok(Ok(Some(c))) => c,
+            Ok(Ok(None)) => return Ok(false),
+            Ok(Err(e)) => {
+                tracing::error!("Failed to read chunk from response body: {}", e);
+                return Err(classify_error(&e));
+            }
+            Err(_) => {
+                tracing::warn!("Timed out while reading response body after {:?}", self.response_timeout);
+                return Err(SummaryError::Timeout);
+            }
+        };
+
+        self.received_size += chunk.len();
+        metrics::DOWNLOADED_BYTES_TOTAL.inc_by(chunk.len() as u64);
+        if self.content_length_limit != 0 && self.received_size > self.content_length_limit {
+            tracing::warn!("Response body exceeded the content length limit of {:?} bytes", self.content_length_limit);
+            return Err(SummaryError::BodyTooLarge(self.content_length_limit));
+        }
+
+        buf.extend_from_slice(&chunk);
+        Ok(true)
+    }
+
+    pub fn error_for_status(self) -> Result<Self, SummaryError> {
+        let status = self.response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            tracing::debug!("'{}' has not been modified", self.response.url());
+            return Err(SummaryError::NotModified);
+        }
+
+        if !status.is_success() {
+            tracing::debug!("Non-success status code from '{}': {}", self.response.url(), status);
+            return Err(SummaryError::UpstreamStatus(status.as_u16()));
+        }
+
+        Ok(self)
+    }
+
+    /// Remaining freshness lifetime of the response in seconds, `None` if it must not be cached
+    pub fn ttl(&self) -> Option<u64> {
+        freshness::remaining_lifetime(self.response.headers(), SystemTime::now())
+    }
+
+    pub fn validators(&self) -> Option<Validators> {
+        let header = |name| self.response.headers().get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+        let validators = Validators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+
+        (validators != Validators::default()).then_some(validators)
+    }
+
+    /// Size of the body as declared by upstream, unknown when it is compressed or chunked
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    /// Name of the file from Content-Disposition, or the last segment of the URL path
+    pub fn filename(&self) -> Option<String> {
+        let disposition = self.response.headers().get(CONTENT_DISPOSITION).and_then(|v| v.to_str().ok());
+        if let Some(name) = disposition.and_then(disposition_filename) {
+            return Some(name);
+        }
+
+        let segment = self.url().path_segments()?.rev().find(|s| !s.is_empty())?;
+        decode(segment).ok().map(|s| s.into_owned())
+    }
+
+    // Top-level domain of the final URL, a hint for the charset of pages that do not declare one
+    fn tld(&self) -> Option<String> {
+        self.url().host_str().and_then(|h| h.rsplit('.').next()).map(|t| t.to_string())
     }
 
     pub fn content_type(&self) -> Option<String> {
@@ -169,13 +516,82 @@ impl ResponseWrapper {
     }
 }
 
+// A body start tag ends the head as well, for pages that leave out </head>
+const HEAD_END_TAGS: [&[u8]; 2] = [b"</head", b"<body"];
+
+fn head_end(bytes: &[u8]) -> Option<usize> {
+    (0..bytes.len()).find(|&i| {
+        HEAD_END_TAGS
+            .iter()
+            .any(|tag| bytes[i..].get(..tag.len()).is_some_and(|b| b.eq_ignore_ascii_case(tag)))
+    })
+}
+
+/// Whether enough of the page was received to summarize it. Otherwise `searched` is moved to where the search continues once
+/// more bytes were received, so that the bytes are only searched once.
+fn head_received(received: &[u8], searched: &mut usize, budget: usize) -> bool {
+    if let Some(end) = head_end(&received[*searched..]) {
+        tracing::debug!("Found the end of <head> after {} bytes", *searched + end);
+        return true;
+    }
+    if received.len() >= budget {
+        tracing::debug!("No end of <head> within {} bytes, using what was received", budget);
+        return true;
+    }
+
+    // The tag may be split between chunks
+    *searched = received.len().saturating_sub(HEAD_END_TAGS[0].len() - 1);
+    false
+}
+
+// The extended `filename*` parameter (RFC 6266) is preferred, it is the one that can carry non-ASCII names
+fn disposition_filename(disposition: &str) -> Option<String> {
+    let params: Vec<(String, &str)> = disposition
+        .split(';')
+        .skip(1)
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim()))
+        .collect();
+
+    let extended = params.iter().find(|(k, _)| k == "filename*").and_then(|(_, v)| {
+        let (charset, rest) = v.split_once('\'')?;
+        let (_, encoded) = rest.split_once('\'')?;
+        if !charset.eq_ignore_ascii_case("utf-8") {
+            return None;
+        }
+        decode(encoded).ok().map(|s| s.into_owned())
+    });
+
+    extended
+        .or_else(|| params.iter().find(|(k, _)| k == "filename").map(|(_, v)| v.trim_matches('"').to_string()))
+        .filter(|name| !name.is_empty())
+}
+
 impl From<Response> for ResponseWrapper {
     fn from(response: Response) -> Self {
         Self::new(response)
     }
 }
 
-pub async fn get(url: &str, options: &RequestOptions) -> Result<ResponseWrapper> {
+pub async fn get(url: &str, options: &RequestOptions) -> Result<ResponseWrapper, SummaryError> {
+    let started_at = Instant::now();
+    let result = send(url, options).await;
+
+    let outcome = match &result {
+        Ok(_) => "success",
+        Err(e) => {
+            metrics::UPSTREAM_ERRORS_TOTAL.with_label_values(&[e.code()]).inc();
+            "error"
+        }
+    };
+    metrics::UPSTREAM_REQUEST_DURATION
+        .with_label_values(&[outcome])
+        .observe(started_at.elapsed().as_secs_f64());
+
+    result
+}
+
+async fn send(url: &str, options: &RequestOptions) -> Result<ResponseWrapper, SummaryError> {
     let mut headers = HeaderMap::new();
     headers.insert(
         "Accept",
@@ -187,7 +603,7 @@ pub async fn get(url: &str, options: &RequestOptions) -> Result<ResponseWrapper>
             .unwrap(),
     );
 
-    let lang = options.lang.as_ref().unwrap_or(&CONFIG.general.default_lang);
+    let lang = options.lang.clone().unwrap_or_else(|| CONFIG.load().general.default_lang.clone());
 
     headers.insert("Accept-Language", lang.parse().unwrap());
 
@@ -197,48 +613,386 @@ pub async fn get(url: &str, options: &RequestOptions) -> Result<ResponseWrapper>
         headers.insert("User-Agent", ua.parse().unwrap());
     }
 
+    // reqwest drops Content-Length when it transparently decompresses a body
+    if options.content_length_required == Some(true) {
+        headers.insert("Accept-Encoding", "identity".parse().unwrap());
+    }
+
+    if let Some(validators) = &options.validators {
+        if let Some(etag) = &validators.etag {
+            headers.insert(IF_NONE_MATCH, etag.parse().unwrap());
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            headers.insert(IF_MODIFIED_SINCE, last_modified.parse().unwrap());
+        }
+    }
+
     if let Some(custom_headers) = &options.headers {
         headers.extend(custom_headers.clone());
     }
 
-    let request = CLIENT.get(url).headers(headers);
+    let (response, redirects) = follow(Method::GET, url, headers, options).await?;
+
+    if options.content_length_required == Some(true) && response.content_length().is_none() {
+        tracing::info!("Response from '{}' has no Content-Length header", url);
+        return Err(SummaryError::ContentLengthRequired);
+    }
+
+    Ok(ResponseWrapper {
+        content_length: response.content_length(),
+        response,
+        redirects,
+        content_length_limit: options.content_length_limit(),
+        response_timeout: options.response_timeout(),
+        head: Vec::new(),
+        received_size: 0,
+    })
+}
+
+pub async fn head(url: &str) -> Result<HeaderMap, SummaryError> {
+    let (response, _) = follow(Method::HEAD, url, HeaderMap::new(), &RequestOptions::default()).await?;
+    Ok(response.headers().clone())
+}
 
-    let response = request.send().await;
-    if let Err(e) = &response {
-        let is_ignore_error = 'err: {
-            let ReqwestMiddlewareError::Reqwest(inner) = e else { break 'err false };
-            let Some(hyper_err) = inner.source().and_then(|s| s.downcast_ref::<HyperUtilError>()) else {
-                break 'err false;
-            };
-            if let Some(source) = hyper_err.source() {
-                source.to_string() == "tcp connect error"
-            } else {
-                false
+/// Sends the request and follows redirects one hop at a time. Every hop goes through the ACL middleware and resolver again,
+/// so a redirect cannot reach a destination that would have been rejected if it had been requested directly.
+async fn follow(mut method: Method, url: &str, mut headers: HeaderMap, options: &RequestOptions) -> Result<(Response, Vec<Redirect>), SummaryError> {
+    let client = CLIENT.load_full();
+    let max_hops = CONFIG.load().general.max_redirect_hops;
+    let response_timeout = options.response_timeout();
+    let operation_timeout = options.operation_timeout();
+    let started_at = Instant::now();
+
+    let mut current = Url::parse(url).map_err(|e| SummaryError::NetworkError(e.to_string()))?;
+    let mut redirects: Vec<Redirect> = Vec::new();
+
+    loop {
+        // The operation timeout covers the whole chain, not each hop
+        let remaining = operation_timeout.saturating_sub(started_at.elapsed());
+        client.check_target(&current).await?;
+
+        let request = client
+            .client
+            .request(method.clone(), current.clone())
+            .headers(headers.clone())
+            .timeout(remaining);
+
+        let response = match tokio::time::timeout(response_timeout.min(remaining), request.send()).await {
+            Ok(r) => r,
+            Err(_) => {
+                tracing::info!("Timed out waiting for response from '{}' after {:?}", current, response_timeout);
+                return Err(SummaryError::Timeout);
             }
         };
 
-        if is_ignore_error {
-            tracing::info!(
-                "Failed to resolve host for '{}'. The resolved IP address may have been blocked by ACL.",
-                url
-            );
-        } else {
-            let mut root_cause: &dyn std::error::Error = &e;
-            while let Some(source) = root_cause.source() {
-                root_cause = source;
+        let response = match response {
+            Ok(r) => r,
+            Err(e) => {
+                let error = classify_middleware_error(&e);
+                if error == SummaryError::AclBlocked {
+                    tracing::info!("Request to '{}' was blocked by ACL: {}", current, e);
+                } else {
+                    let mut root_cause: &dyn std::error::Error = &e;
+                    while let Some(source) = root_cause.source() {
+                        root_cause = source;
+                    }
+                    tracing::error!("Failed to fetch '{}' -> {}", current, root_cause);
+                }
+
+                return Err(error);
             }
-            tracing::error!("Failed to fetch '{}' -> {}", url, root_cause);
+        };
+
+        let status = response.status();
+        let location = response.headers().get(LOCATION).and_then(|v| v.to_str().ok());
+        let follow_redirects = options.follow_redirects != Some(false);
+        let Some(next) = next_hop(&current, status, location, &redirects, max_hops, follow_redirects)? else {
+            return Ok((response, redirects));
+        };
+
+        // Boxed because robots.txt is fetched through `get` as well, which never checks robots.txt itself
+        if options.check_robots_txt && !Box::pin(robotstxt::is_allowed_scraping(&next)).await {
+            tracing::info!("Scraping disallowed by robots.txt: {} (redirected from {})", next, current);
+            return Err(SummaryError::RobotsDisallowed);
         }
+
+        strip_credentials(&mut headers, &current, &next);
+        method = redirect_method(status, method);
+
+        tracing::debug!("Following {} redirect from '{}' to '{}'", status.as_u16(), current, next);
+        redirects.push(Redirect {
+            url: current.to_string(),
+            status: status.as_u16(),
+            location: next.to_string(),
+        });
+        current = next;
+    }
+}
+
+/// Where a response redirects to, `None` if it is not a redirect to follow. Fails for redirects that must not be followed.
+fn next_hop(
+    current: &Url,
+    status: StatusCode,
+    location: Option<&str>,
+    redirects: &[Redirect],
+    max_hops: u32,
+    follow_redirects: bool,
+) -> Result<Option<Url>, SummaryError> {
+    // 304 and 300 are not redirects to follow, and neither is a redirect without a location
+    let location = match (status, location) {
+        (
+            StatusCode::MOVED_PERMANENTLY |
+            StatusCode::FOUND |
+            StatusCode::SEE_OTHER |
+            StatusCode::TEMPORARY_REDIRECT |
+            StatusCode::PERMANENT_REDIRECT,
+            Some(location),
+        ) if follow_redirects => location,
+        _ => return Ok(None),
+    };
+
+    // Relative locations are resolved against the URL that was redirected
+    let next = current.join(location).map_err(|e| {
+        tracing::info!("Invalid redirect location '{}' from '{}': {}", location, current, e);
+        SummaryError::invalid_response(format!("invalid redirect location '{}'", location))
+    })?;
+
+    if !matches!(next.scheme(), "http" | "https") {
+        tracing::info!("Refusing to follow redirect from '{}' to '{}'", current, next);
+        return Err(SummaryError::AclBlocked);
+    }
+
+    if redirects.len() as u32 >= max_hops {
+        tracing::info!("Too many redirects at '{}', the limit is {}", current, max_hops);
+        return Err(SummaryError::TooManyRedirects(max_hops));
     }
 
-    Ok(response?.into())
+    if next == *current || redirects.iter().any(|r| r.url == next.as_str()) {
+        tracing::info!("Redirect loop detected at '{}'", next);
+        return Err(SummaryError::RedirectLoop);
+    }
+
+    Ok(Some(next))
 }
 
-pub async fn head(url: &str) -> Result<HeaderMap> {
-    let response = CLIENT.head(url).send().await?;
-    Ok(response.headers().clone())
+/// 303 asks for the result to be retrieved with GET, the other redirects repeat the request as it was
+fn redirect_method(status: StatusCode, method: Method) -> Method {
+    match status {
+        StatusCode::SEE_OTHER if method != Method::HEAD => Method::GET,
+        _ => method,
+    }
+}
+
+/// Credentials are only meant for the origin they were given for
+fn strip_credentials(headers: &mut HeaderMap, current: &Url, next: &Url) {
+    if next.origin() != current.origin() {
+        for name in [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION] {
+            headers.remove(name);
+        }
+    }
+}
+
+fn classify_middleware_error(error: &ReqwestMiddlewareError) -> SummaryError {
+    match error {
+        // The ACL middleware is the only middleware, it rejects denied schemes, hosts, ports and IP literals
+        ReqwestMiddlewareError::Middleware(_) => SummaryError::AclBlocked,
+        ReqwestMiddlewareError::Reqwest(inner) => classify_error(inner),
+    }
+}
+
+fn classify_error(error: &reqwest::Error) -> SummaryError {
+    if error.is_timeout() {
+        return SummaryError::Timeout;
+    }
+
+    // Names whose addresses are all denied fail to connect with the error of the resolver somewhere in the chain.
+    // Any other connect error, such as a refused connection, is a plain network error.
+    let mut source = error.source();
+    while let Some(e) = source {
+        if e.is::<resolver::AclDenied>() {
+            return SummaryError::AclBlocked;
+        }
+        source = e.source();
+    }
+
+    SummaryError::NetworkError(error.to_string())
+}
+
+/// Classifies a failure to read or decode a body, which is only an invalid response if the body arrived and could not be decoded
+pub fn body_error(error: reqwest::Error) -> SummaryError {
+    if error.is_decode() {
+        SummaryError::invalid_response(error)
+    } else {
+        classify_error(&error)
+    }
+}
+
+/// Checks that the DNS resolver can be built from the system configuration
+pub fn check_dns_resolver() -> anyhow::Result<()> {
+    resolver::create_resolver().map(|_| ())
 }
 
 pub fn add_cookie(url: &Url, cookie_str: &str) {
     COOKIE_JAR.add_cookie_str(cookie_str, url);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn redirect(from: &str, to: &str) -> Redirect {
+        Redirect {
+            url: from.to_string(),
+            status: 302,
+            location: to.to_string(),
+        }
+    }
+
+    #[test]
+    fn resolves_relative_locations() {
+        let current = url("https://example.com/a/b?x=1");
+        let hop = |location| next_hop(&current, StatusCode::FOUND, Some(location), &[], 5, true).map(|u| u.map(String::from));
+        assert_eq!(hop("c"), Ok(Some("https://example.com/a/c".to_string())));
+        assert_eq!(hop("/c?y=2"), Ok(Some("https://example.com/c?y=2".to_string())));
+        assert_eq!(hop("../c"), Ok(Some("https://example.com/c".to_string())));
+        assert_eq!(hop("//cdn.example.net/c"), Ok(Some("https://cdn.example.net/c".to_string())));
+        assert_eq!(hop("http://other.example/"), Ok(Some("http://other.example/".to_string())));
+        assert_eq!(hop("ftp://example.com/file"), Err(SummaryError::AclBlocked));
+        assert!(matches!(hop("http://[::1"), Err(SummaryError::InvalidResponse(_))));
+    }
+
+    #[test]
+    fn only_follows_redirects_with_a_location() {
+        let current = url("https://example.com/");
+        let hop = |status, location| next_hop(&current, status, location, &[], 5, true);
+        for status in [301, 302, 303, 307, 308] {
+            assert!(
+                matches!(hop(StatusCode::from_u16(status).unwrap(), Some("/next")), Ok(Some(_))),
+                "{}",
+                status
+            );
+        }
+        assert_eq!(hop(StatusCode::MULTIPLE_CHOICES, Some("/next")), Ok(None));
+        assert_eq!(hop(StatusCode::NOT_MODIFIED, Some("/next")), Ok(None));
+        assert_eq!(hop(StatusCode::OK, Some("/next")), Ok(None));
+        assert_eq!(hop(StatusCode::FOUND, None), Ok(None));
+        assert_eq!(next_hop(&current, StatusCode::FOUND, Some("/next"), &[], 5, false), Ok(None));
+    }
+
+    #[test]
+    fn detects_redirect_loops() {
+        let current = url("https://example.com/b");
+        let redirects = [redirect("https://example.com/a", "https://example.com/b")];
+        let hop = |location| next_hop(&current, StatusCode::FOUND, Some(location), &redirects, 5, true);
+        assert_eq!(hop("/b"), Err(SummaryError::RedirectLoop));
+        assert_eq!(hop("/a"), Err(SummaryError::RedirectLoop));
+        assert!(matches!(hop("/c"), Ok(Some(_))));
+    }
+
+    #[test]
+    fn limits_redirect_hops() {
+        let current = url("https://example.com/2");
+        let redirects = [
+            redirect("https://example.com/0", "https://example.com/1"),
+            redirect("https://example.com/1", "https://example.com/2"),
+        ];
+        let hop = |max_hops| next_hop(&current, StatusCode::FOUND, Some("/3"), &redirects, max_hops, true);
+        assert!(matches!(hop(3), Ok(Some(_))));
+        assert_eq!(hop(2), Err(SummaryError::TooManyRedirects(2)));
+
+        let first = next_hop(&url("https://example.com/"), StatusCode::FOUND, Some("/next"), &[], 0, true);
+        assert_eq!(first, Err(SummaryError::TooManyRedirects(0)));
+    }
+
+    #[test]
+    fn see_other_switches_to_get() {
+        assert_eq!(redirect_method(StatusCode::SEE_OTHER, Method::POST), Method::GET);
+        assert_eq!(redirect_method(StatusCode::SEE_OTHER, Method::GET), Method::GET);
+        assert_eq!(redirect_method(StatusCode::SEE_OTHER, Method::HEAD), Method::HEAD);
+        assert_eq!(redirect_method(StatusCode::TEMPORARY_REDIRECT, Method::POST), Method::POST);
+        assert_eq!(redirect_method(StatusCode::PERMANENT_REDIRECT, Method::HEAD), Method::HEAD);
+    }
+
+    #[test]
+    fn strips_credentials_on_cross_origin_hops() {
+        let headers = || {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, "Bearer secret".parse().unwrap());
+            headers.insert(COOKIE, "session=1".parse().unwrap());
+            headers.insert(PROXY_AUTHORIZATION, "Basic secret".parse().unwrap());
+            headers.insert("Accept", "text/html".parse().unwrap());
+            headers
+        };
+        let current = url("https://example.com/a");
+
+        let mut same_origin = headers();
+        strip_credentials(&mut same_origin, &current, &url("https://example.com/b"));
+        assert_eq!(same_origin, headers());
+
+        for next in ["https://other.example/a", "http://example.com/a", "https://example.com:8443/a"] {
+            let mut cross_origin = headers();
+            strip_credentials(&mut cross_origin, &current, &url(next));
+            assert_eq!(cross_origin.len(), 1, "{}", next);
+            assert!(cross_origin.contains_key("Accept"));
+        }
+    }
+
+    /// Feeds the chunks like `head_text` does, returns how many bytes were received when it stopped
+    fn received_until_head_end(chunks: &[&[u8]], budget: usize) -> usize {
+        let mut received = Vec::new();
+        let mut searched = 0;
+        for chunk in chunks {
+            received.extend_from_slice(chunk);
+            if head_received(&received, &mut searched, budget) {
+                break;
+            }
+        }
+        received.len()
+    }
+
+    #[test]
+    fn finds_the_end_of_head() {
+        assert_eq!(head_end(b"<html><head></head><body>"), Some(12));
+        assert_eq!(head_end(b"<HTML><HEAD></HEAD>"), Some(12));
+        assert_eq!(head_end(b"<title>x</title><BODY class=a>"), Some(16));
+        assert_eq!(head_end(b"<html><head><title>x</title>"), None);
+        assert_eq!(head_end(b"</hea"), None);
+        assert_eq!(head_end(b""), None);
+    }
+
+    #[test]
+    fn stops_reading_at_the_end_of_head() {
+        let chunks: &[&[u8]] = &[b"<html><head><title>x</title>", b"</head>", b"<body>content</body>"];
+        assert_eq!(received_until_head_end(chunks, 1024), 35);
+
+        let chunks: &[&[u8]] = &[b"<html><head><title>x</title>", b"</HEAD>", b"<BODY>content</BODY>"];
+        assert_eq!(received_until_head_end(chunks, 1024), 35);
+    }
+
+    #[test]
+    fn finds_tags_split_between_chunks() {
+        let chunks: &[&[u8]] = &[b"<head><title>x</title></h", b"ead>", b"<body>"];
+        assert_eq!(received_until_head_end(chunks, 1024), 29);
+
+        let chunks: &[&[u8]] = &[b"<head><title>x</title></HEA", b"D><body>", b"content"];
+        assert_eq!(received_until_head_end(chunks, 1024), 35);
+
+        let chunks: &[&[u8]] = &[b"<title>x</title><bo", b"dy>", b"content"];
+        assert_eq!(received_until_head_end(chunks, 1024), 22);
+
+        let page = b"<head><title>x</title></head><body>content</body>";
+        let bytes = page.iter().map(std::slice::from_ref).collect::<Vec<_>>();
+        assert_eq!(received_until_head_end(&bytes, 1024), 28);
+    }
+
+    #[test]
+    fn reads_pages_without_head_end_up_to_the_budget() {
+        let chunks: &[&[u8]] = &[b"<html><title>x</title>", b"<p>no head</p>", b"</html>"];
+        assert_eq!(received_until_head_end(chunks, 1024), 43);
+        assert_eq!(received_until_head_end(chunks, 30), 36);
+    }
+}
@@ -0,0 +1,136 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use once_cell::sync::Lazy;
+use regex::bytes::Regex;
+
+// Matches both `<meta charset="...">` and `<meta http-equiv="Content-Type" content="text/html; charset=...">`
+static META_CHARSET_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)<meta\s[^>]*?charset\s*=\s*["']?\s*(?<charset>[a-z0-9_:.\-]+)"#).unwrap());
+
+// Same as the prescan length defined in the HTML Standard
+const SNIFF_LENGTH: usize = 1024;
+
+pub fn decode(bytes: &[u8], content_type: Option<&str>, tld: Option<&str>) -> String {
+    let encoding = detect(bytes, content_type, tld);
+    tracing::debug!("Decoding response body as {}", encoding.name());
+
+    let (text, _, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        tracing::debug!("Response body contained malformed sequences for {}", encoding.name());
+    }
+
+    text.into_owned()
+}
+
+fn detect(bytes: &[u8], content_type: Option<&str>, tld: Option<&str>) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+
+    if let Some(encoding) = content_type.and_then(from_content_type) {
+        return encoding;
+    }
+
+    if let Some(encoding) = from_meta(bytes) {
+        return encoding;
+    }
+
+    if str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(tld.map(|t| t.as_bytes()), true)
+}
+
+fn from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        if !key.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+
+        Encoding::for_label(value.trim().trim_matches(|c| c == '"' || c == '\'').as_bytes())
+    })
+}
+
+fn from_meta(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(SNIFF_LENGTH)];
+    let label = META_CHARSET_REGEX.captures(head)?.name("charset")?.as_bytes();
+    let encoding = Encoding::for_label(label)?;
+
+    // A document that managed to declare itself in ASCII cannot be UTF-16, so the HTML Standard maps it to UTF-8
+    if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+        return Some(UTF_8);
+    }
+
+    Some(encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{EUC_JP, SHIFT_JIS, UTF_16LE, WINDOWS_1252};
+
+    const JAPANESE: &str = "<html><head><title>日本語のページ</title></head><body><p>吾輩は猫である。名前はまだ無い。どこで生れたかとんと見当がつかぬ。\
+                            何でも薄暗いじめじめした所でニャーニャー泣いていた事だけは記憶している。</p></body></html>";
+
+    fn encode(encoding: &'static Encoding, text: &str) -> Vec<u8> {
+        encoding.encode(text).0.into_owned()
+    }
+
+    #[test]
+    fn guesses_shift_jis_without_a_declaration() {
+        let bytes = encode(SHIFT_JIS, JAPANESE);
+        assert_eq!(detect(&bytes, None, Some("jp")), SHIFT_JIS);
+        assert_eq!(decode(&bytes, Some("text/html"), Some("jp")), JAPANESE);
+    }
+
+    #[test]
+    fn guesses_euc_jp_without_a_declaration() {
+        let bytes = encode(EUC_JP, JAPANESE);
+        assert_eq!(detect(&bytes, None, Some("jp")), EUC_JP);
+    }
+
+    #[test]
+    fn plain_utf8_is_not_guessed() {
+        assert_eq!(detect(JAPANESE.as_bytes(), None, Some("jp")), UTF_8);
+    }
+
+    #[test]
+    fn header_charset_overrides_meta() {
+        let bytes = br#"<html><head><meta charset="euc-jp"></head></html>"#;
+        assert_eq!(detect(bytes, Some("text/html; charset=Shift_JIS"), None), SHIFT_JIS);
+        assert_eq!(detect(bytes, Some("text/html; charset=\"shift_jis\""), None), SHIFT_JIS);
+        // An unknown label in the header falls through to the document
+        assert_eq!(detect(bytes, Some("text/html; charset=bogus"), None), EUC_JP);
+    }
+
+    #[test]
+    fn reads_meta_charset_and_http_equiv() {
+        let charset = br#"<html><head><meta charset="Shift_JIS"></head></html>"#;
+        assert_eq!(detect(charset, Some("text/html"), None), SHIFT_JIS);
+
+        let http_equiv = br#"<html><head><META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=euc-jp"></head></html>"#;
+        assert_eq!(detect(http_equiv, Some("text/html"), None), EUC_JP);
+
+        let latin1 = br#"<meta http-equiv='content-type' content='text/html;charset=iso-8859-1'>"#;
+        assert_eq!(detect(latin1, None, None), WINDOWS_1252);
+    }
+
+    #[test]
+    fn meta_declaring_utf16_means_utf8() {
+        let bytes = br#"<meta charset="utf-16le"><title>x</title>"#;
+        assert_eq!(detect(bytes, None, None), UTF_8);
+    }
+
+    #[test]
+    fn bom_overrides_everything() {
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend_from_slice(br#"<meta charset="shift_jis">"#);
+        assert_eq!(detect(&bytes, Some("text/html; charset=euc-jp"), Some("jp")), UTF_8);
+
+        let utf16 = [0xFF, 0xFE, b'<', 0, b'p', 0, b'>', 0];
+        assert_eq!(detect(&utf16, Some("text/html; charset=shift_jis"), None), UTF_16LE);
+    }
+}
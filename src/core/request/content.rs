@@ -0,0 +1,137 @@
+use infer::MatcherType;
+
+/// What a response body holds, which decides how it is summarized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContentKind {
+    Html,
+    Image,
+    Video,
+    Audio,
+    Pdf,
+    /// Text that is not a page, such as JSON or plain text
+    Text,
+    Binary,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Content {
+    pub kind: ContentKind,
+    /// Media type from the magic bytes if they were recognized, otherwise from the Content-Type header
+    pub mime: Option<String>,
+}
+
+pub fn detect(content_type: Option<&str>, head: &[u8]) -> Content {
+    let declared = content_type
+        .and_then(|c| c.split(';').next())
+        .map(|c| c.trim().to_ascii_lowercase())
+        .filter(|c| !c.is_empty());
+
+    let mut markup = None;
+
+    // Files are often served as application/octet-stream or with a type guessed from the extension, their signature is more reliable
+    if let Some(sniffed) = infer::get(head) {
+        let kind = match sniffed.matcher_type() {
+            MatcherType::Image => Some(ContentKind::Image),
+            MatcherType::Video => Some(ContentKind::Video),
+            MatcherType::Audio => Some(ContentKind::Audio),
+            _ if sniffed.mime_type() == "application/pdf" => Some(ContentKind::Pdf),
+            // Markup is only recognized by heuristics, the header is a better source for it unless it is a generic one
+            MatcherType::Text => {
+                markup = Some(sniffed.mime_type().to_string());
+                None
+            }
+            _ => Some(ContentKind::Binary),
+        };
+
+        if let Some(kind) = kind {
+            return Content {
+                kind,
+                mime: Some(sniffed.mime_type().to_string()),
+            };
+        }
+    }
+
+    let declared = match declared.as_deref() {
+        Some("application/octet-stream") if markup.is_some() => markup,
+        _ => declared,
+    };
+
+    let kind = match declared.as_deref() {
+        // Pages without a Content-Type have always been summarized as HTML
+        None => ContentKind::Html,
+        Some(mime) if mime.contains("html") => ContentKind::Html,
+        Some(mime) if mime.starts_with("image/") => ContentKind::Image,
+        Some(mime) if mime.starts_with("video/") => ContentKind::Video,
+        Some(mime) if mime.starts_with("audio/") => ContentKind::Audio,
+        Some("application/pdf") => ContentKind::Pdf,
+        Some(mime) if mime.starts_with("text/") || mime.ends_with("json") || mime.ends_with("xml") || mime.ends_with("javascript") => {
+            ContentKind::Text
+        }
+        Some(_) => ContentKind::Binary,
+    };
+
+    Content { kind, mime: declared }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    const PDF: &[u8] = b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n";
+    const ZIP: &[u8] = b"PK\x03\x04\x14\0\0\0\x08\0";
+    const HTML: &[u8] = b"<!DOCTYPE html>\n<html><head><title>Page</title>";
+
+    // Content-Type header, start of the body, and the expected kind and media type
+    type Case = (Option<&'static str>, &'static [u8], ContentKind, Option<&'static str>);
+
+    fn kind(content_type: Option<&str>, head: &[u8]) -> (ContentKind, Option<String>) {
+        let content = detect(content_type, head);
+        (content.kind, content.mime)
+    }
+
+    #[test]
+    fn detects_content_kinds() {
+        let cases: &[Case] = &[
+            // The signature wins over the declared type
+            (Some("text/html; charset=utf-8"), PNG, ContentKind::Image, Some("image/png")),
+            (Some("application/octet-stream"), PNG, ContentKind::Image, Some("image/png")),
+            (Some("application/octet-stream"), PDF, ContentKind::Pdf, Some("application/pdf")),
+            (Some("application/pdf"), PDF, ContentKind::Pdf, Some("application/pdf")),
+            (Some("text/html"), ZIP, ContentKind::Binary, Some("application/zip")),
+            // Markup is taken from the header, unless the header is a generic one
+            (Some("application/octet-stream"), HTML, ContentKind::Html, Some("text/html")),
+            (Some("text/plain"), HTML, ContentKind::Text, Some("text/plain")),
+            (Some("Text/HTML; charset=Shift_JIS"), HTML, ContentKind::Html, Some("text/html")),
+            (None, HTML, ContentKind::Html, None),
+            // Without a recognized signature the declared type decides
+            (Some("application/pdf"), b"not a pdf", ContentKind::Pdf, Some("application/pdf")),
+            (Some("application/json"), b"{}", ContentKind::Text, Some("application/json")),
+            (Some("image/svg+xml"), b"<svg></svg>", ContentKind::Image, Some("image/svg+xml")),
+            (
+                Some("application/octet-stream"),
+                b"\x00\x01\x02\x03",
+                ContentKind::Binary,
+                Some("application/octet-stream"),
+            ),
+            (
+                Some("application/x-unknown"),
+                b"\x00\x01\x02\x03",
+                ContentKind::Binary,
+                Some("application/x-unknown"),
+            ),
+            (None, b"", ContentKind::Html, None),
+            (Some(""), b"", ContentKind::Html, None),
+        ];
+
+        for (content_type, head, expected, mime) in cases {
+            assert_eq!(
+                kind(*content_type, head),
+                (*expected, mime.map(String::from)),
+                "{:?} {:?}",
+                content_type,
+                head
+            );
+        }
+    }
+}
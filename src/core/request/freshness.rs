@@ -0,0 +1,188 @@
+use reqwest::header::{AGE, CACHE_CONTROL, DATE, EXPIRES, HeaderMap, LAST_MODIFIED};
+use std::time::SystemTime;
+
+// Used when the response carries no explicit expiration time and no Last-Modified to base a heuristic on
+const DEFAULT_LIFETIME: u64 = 300;
+
+#[derive(Debug, Default)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    private: bool,
+    max_age: Option<u64>,
+    s_maxage: Option<u64>,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut cc = CacheControl::default();
+
+        // Cache-Control may be split over several header lines
+        let directives = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','));
+
+        for directive in directives {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+
+            match name.to_ascii_lowercase().as_str() {
+                "no-store" => cc.no_store = true,
+                // The qualified forms only apply to the listed header fields, which are never stored
+                "no-cache" if value.is_none() => cc.no_cache = true,
+                "private" if value.is_none() => cc.private = true,
+                // A delta-seconds value that cannot be parsed makes the response stale (RFC 9111 section 1.2.2)
+                "max-age" => cc.max_age = Some(value.and_then(|v| v.parse().ok()).unwrap_or(0)),
+                "s-maxage" => cc.s_maxage = Some(value.and_then(|v| v.parse().ok()).unwrap_or(0)),
+                _ => {}
+            }
+        }
+
+        cc
+    }
+}
+
+fn http_date(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<SystemTime> {
+    headers.get(name)?.to_str().ok().and_then(|v| httpdate::parse_http_date(v).ok())
+}
+
+fn seconds_between(earlier: SystemTime, later: SystemTime) -> u64 {
+    later.duration_since(earlier).map_or(0, |d| d.as_secs())
+}
+
+/// Remaining freshness lifetime of a response in seconds, as a shared cache would calculate it (RFC 9111 section 4.2).
+/// Returns `None` if the response must not be stored at all.
+pub fn remaining_lifetime(headers: &HeaderMap, now: SystemTime) -> Option<u64> {
+    let cc = CacheControl::parse(headers);
+
+    // no-cache requires revalidation before every use, which we cannot do for a summary, so it is treated like no-store
+    if cc.no_store || cc.private || cc.no_cache {
+        return None;
+    }
+
+    let date = http_date(headers, DATE);
+    let lifetime = if let Some(s_maxage) = cc.s_maxage {
+        s_maxage
+    } else if let Some(max_age) = cc.max_age {
+        max_age
+    } else if let Some(expires) = headers.get(EXPIRES) {
+        // An invalid Expires value, such as "0", means the response is already expired
+        match expires.to_str().ok().and_then(|v| httpdate::parse_http_date(v).ok()) {
+            Some(expires) => seconds_between(date.unwrap_or(now), expires),
+            None => 0,
+        }
+    } else if let Some(last_modified) = http_date(headers, LAST_MODIFIED) {
+        // Heuristic freshness of 10% of the time since the last modification (RFC 9111 section 4.2.2)
+        seconds_between(last_modified, date.unwrap_or(now)) / 10
+    } else {
+        DEFAULT_LIFETIME
+    };
+
+    let age = headers
+        .get(AGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(0);
+    let apparent_age = date.map_or(0, |date| seconds_between(date, now));
+    let current_age = age.max(apparent_age);
+
+    Some(lifetime.saturating_sub(current_age))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn now() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    }
+
+    /// An HTTP date the given number of seconds away from `now()`
+    fn at(offset: i64) -> String {
+        let duration = Duration::from_secs(offset.unsigned_abs());
+        httpdate::fmt_http_date(if offset < 0 { now() - duration } else { now() + duration })
+    }
+
+    // Name, response headers and the expected remaining lifetime
+    type Case = (&'static str, Vec<(&'static str, String)>, Option<u64>);
+
+    fn headers(pairs: &[(&str, String)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(reqwest::header::HeaderName::from_bytes(name.as_bytes()).unwrap(), value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn remaining_lifetime_of_responses() {
+        let cases: Vec<Case> = vec![
+            ("no headers", vec![], Some(DEFAULT_LIFETIME)),
+            ("max-age", vec![("cache-control", "public, max-age=600".into())], Some(600)),
+            (
+                "s-maxage over max-age",
+                vec![("cache-control", "max-age=600, s-maxage=60".into())],
+                Some(60),
+            ),
+            (
+                "s-maxage on another line",
+                vec![("cache-control", "max-age=600".into()), ("cache-control", "s-maxage=60".into())],
+                Some(60),
+            ),
+            ("invalid max-age", vec![("cache-control", "max-age=soon".into())], Some(0)),
+            (
+                "max-age over expires",
+                vec![("cache-control", "max-age=600".into()), ("expires", at(60))],
+                Some(600),
+            ),
+            ("expires minus date", vec![("date", at(-100)), ("expires", at(500))], Some(500)),
+            ("expires without date", vec![("expires", at(500))], Some(500)),
+            ("expires in the past", vec![("date", at(0)), ("expires", at(-60))], Some(0)),
+            ("invalid expires", vec![("expires", "0".into())], Some(0)),
+            ("age", vec![("cache-control", "max-age=600".into()), ("age", "100".into())], Some(500)),
+            (
+                "age over max-age",
+                vec![("cache-control", "max-age=600".into()), ("age", "900".into())],
+                Some(0),
+            ),
+            (
+                "apparent age over age",
+                vec![("cache-control", "max-age=600".into()), ("date", at(-200)), ("age", "100".into())],
+                Some(400),
+            ),
+            (
+                "last-modified heuristic",
+                vec![("date", at(0)), ("last-modified", at(-10_000))],
+                Some(1_000),
+            ),
+            ("last-modified without date", vec![("last-modified", at(-10_000))], Some(1_000)),
+            (
+                "expires over last-modified",
+                vec![("expires", at(60)), ("last-modified", at(-10_000))],
+                Some(60),
+            ),
+            ("no-store", vec![("cache-control", "no-store, max-age=600".into())], None),
+            ("no-cache", vec![("cache-control", "No-Cache".into())], None),
+            (
+                "qualified no-cache",
+                vec![("cache-control", "no-cache=\"set-cookie\", max-age=600".into())],
+                Some(600),
+            ),
+            ("private", vec![("cache-control", "private, max-age=600".into())], None),
+            (
+                "qualified private",
+                vec![("cache-control", "private=\"x-user\", max-age=600".into())],
+                Some(600),
+            ),
+        ];
+
+        for (name, pairs, expected) in cases {
+            assert_eq!(remaining_lifetime(&headers(&pairs), now()), expected, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+use url::Host;
+
+/// Host name pattern where `*` stands for any run of characters, dots included.
+/// `*.example.com` matches the subdomains of example.com but not example.com itself, `*` matches every host.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostPattern(String);
+
+impl HostPattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let normalized = pattern.trim().trim_end_matches('.').to_ascii_lowercase();
+        if normalized.is_empty() {
+            return Err("must not be empty".to_string());
+        }
+
+        // IPv6 addresses are written in brackets like in URLs, and matched in their compressed form
+        let normalized = match normalized.strip_prefix('[').and_then(|p| p.strip_suffix(']')) {
+            Some(ip) => match Host::parse(&format!("[{}]", ip)) {
+                Ok(host) => host.to_string(),
+                Err(_) => return Err(format!("'{}' is not a valid IPv6 address", pattern)),
+            },
+            None => normalized,
+        };
+
+        if let Some(c) = normalized
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '*' | '[' | ']' | ':')))
+        {
+            return Err(format!("'{}' contains '{}', which cannot appear in a host name", pattern, c));
+        }
+
+        Ok(Self(normalized))
+    }
+
+    /// Patterns that fail to parse are skipped, they are reported when the config is validated
+    pub fn parse_all(patterns: &[String]) -> Vec<Self> {
+        patterns.iter().filter_map(|p| Self::parse(p).ok()).collect()
+    }
+
+    pub fn matches(&self, host: &str) -> bool {
+        wildcard_match(self.0.as_bytes(), host.trim_end_matches('.').to_ascii_lowercase().as_bytes())
+    }
+}
+
+impl fmt::Display for HostPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Backtracks to the last `*` on a mismatch, which is enough since a `*` can absorb any input
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, host: &str) -> bool {
+        HostPattern::parse(pattern).unwrap().matches(host)
+    }
+
+    #[test]
+    fn wildcard_subdomains_exclude_the_domain_itself() {
+        assert!(matches("*.example.com", "www.example.com"));
+        assert!(matches("*.example.com", "a.b.example.com"));
+        assert!(!matches("*.example.com", "example.com"));
+        assert!(!matches("*.example.com", "badexample.com"));
+        assert!(!matches("*.example.com", "example.com.evil.test"));
+    }
+
+    #[test]
+    fn wildcard_backtracks() {
+        assert!(matches("*", "anything.test"));
+        assert!(matches("api-*.example.com", "api-eu-1.example.com"));
+        assert!(matches("*.cdn.*.net", "img.cdn.eu.cdn.provider.net"));
+        assert!(!matches("*.cdn.*.net", "img.cdn.example.com"));
+    }
+
+    #[test]
+    fn trailing_dot_and_case_are_ignored() {
+        assert!(matches("example.com.", "example.com"));
+        assert!(matches("example.com", "Example.COM."));
+        assert!(matches("*.Example.com", "www.example.com."));
+    }
+
+    #[test]
+    fn bracketed_ipv6_is_normalized() {
+        assert!(matches("[::1]", "[::1]"));
+        assert!(matches("[0:0:0:0:0:0:0:1]", "[::1]"));
+        assert!(matches("[FD00::2]", "[fd00::2]"));
+        assert!(!matches("[::1]", "[::2]"));
+        assert!(HostPattern::parse("[zz::1]").is_err());
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        assert!(HostPattern::parse("").is_err());
+        assert!(HostPattern::parse(" . ").is_err());
+        assert!(HostPattern::parse("exa mple.com").is_err());
+        assert!(HostPattern::parse("example.com/path").is_err());
+    }
+}
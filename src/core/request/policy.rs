@@ -0,0 +1,215 @@
+use crate::{config::ISecurity, core::request::pattern::HostPattern};
+use http_acl_reqwest::{AddError, HttpAcl, HttpAclBuilder};
+use ipnet::IpNet;
+use std::{net::IpAddr, ops::RangeInclusive};
+use url::{Host, Url};
+
+/// Destinations allowed and denied in `[security]`. Allowed entries are exceptions to the denied ones and to
+/// `block_non_global_ips`, they do not restrict requests to the allowed destinations.
+#[derive(Debug, Default)]
+pub struct TargetPolicy {
+    allowed_hosts: Vec<HostPattern>,
+    denied_hosts: Vec<HostPattern>,
+    allowed_ip_ranges: Vec<IpNet>,
+    denied_ip_ranges: Vec<IpNet>,
+    allowed_ports: Vec<RangeInclusive<u16>>,
+    denied_ports: Vec<RangeInclusive<u16>>,
+}
+
+impl TargetPolicy {
+    /// Invalid entries are left out, they are reported when the config is validated
+    pub fn new(security: &ISecurity) -> Self {
+        Self {
+            allowed_hosts: HostPattern::parse_all(&security.allowed_hosts),
+            denied_hosts: HostPattern::parse_all(&security.denied_hosts),
+            allowed_ip_ranges: security.allowed_ip_ranges.iter().filter_map(|r| parse_ip_range(r).ok()).collect(),
+            denied_ip_ranges: security.denied_ip_ranges.iter().filter_map(|r| parse_ip_range(r).ok()).collect(),
+            allowed_ports: security.allowed_ports.iter().filter_map(|p| parse_port_range(p).ok()).collect(),
+            denied_ports: security.denied_ports.iter().filter_map(|p| parse_port_range(p).ok()).collect(),
+        }
+    }
+
+    pub fn allows_host(&self, host: &str) -> bool {
+        self.allowed_hosts.iter().any(|p| p.matches(host))
+    }
+
+    pub fn denies_host(&self, host: &str) -> bool {
+        !self.allows_host(host) && self.denied_hosts.iter().any(|p| p.matches(host))
+    }
+
+    pub fn denies_port(&self, port: u16) -> bool {
+        !self.allowed_ports.iter().any(|r| r.contains(&port)) && self.denied_ports.iter().any(|r| r.contains(&port))
+    }
+
+    pub fn denies_ip(&self, ip: &IpAddr) -> bool {
+        !self.allowed_ip_ranges.iter().any(|r| r.contains(ip)) && self.denied_ip_ranges.iter().any(|r| r.contains(ip))
+    }
+
+    /// Checks what can be told from the URL alone, the addresses a host name resolves to are checked when it is requested
+    pub fn denies_url(&self, url: &Url) -> bool {
+        let Some(host) = url.host() else {
+            return false;
+        };
+
+        let name = host.to_string();
+        if self.allows_host(&name) {
+            return false;
+        }
+
+        let ip = match host {
+            Host::Ipv4(ip) => Some(IpAddr::V4(ip)),
+            Host::Ipv6(ip) => Some(IpAddr::V6(ip)),
+            Host::Domain(_) => None,
+        };
+
+        self.denies_host(&name) || url.port_or_known_default().is_some_and(|port| self.denies_port(port)) || ip.is_some_and(|ip| self.denies_ip(&ip))
+    }
+
+    /// Whether addresses have to be looked up to apply the policy to a host name
+    pub fn denies_ips(&self) -> bool {
+        !self.denied_ip_ranges.is_empty()
+    }
+}
+
+/// The IP ranges and ports are enforced by the ACL as well, which also covers the addresses a name resolves to on connect.
+/// Host patterns are not, the ACL only knows exact names.
+pub fn build_acl(security: &ISecurity) -> Result<HttpAcl, AddError> {
+    let policy = TargetPolicy::new(security);
+    let mut builder = acl_builder(security);
+
+    for range in policy.allowed_ip_ranges {
+        builder = builder.add_allowed_ip_range(range)?;
+    }
+    for range in policy.denied_ip_ranges {
+        builder = builder.add_denied_ip_range(range)?;
+    }
+    for range in policy.allowed_ports {
+        builder = builder.add_allowed_port_range(range)?;
+    }
+    for range in policy.denied_ports {
+        builder = builder.add_denied_port_range(range)?;
+    }
+
+    Ok(builder.build())
+}
+
+/// The ACL without the lists, only blocking non-global addresses if configured
+pub fn default_acl(security: &ISecurity) -> HttpAcl {
+    acl_builder(security).build()
+}
+
+fn acl_builder(security: &ISecurity) -> HttpAclBuilder {
+    HttpAcl::builder()
+        .ip_acl_default(true)
+        .port_acl_default(true)
+        .host_acl_default(true)
+        .non_global_ip_ranges(security.block_non_global_ips)
+}
+
+/// A network in CIDR notation, or a single address
+pub fn parse_ip_range(value: &str) -> Result<IpNet, String> {
+    let value = value.trim();
+    if let Ok(ip) = value.parse::<IpAddr>() {
+        return Ok(IpNet::from(ip));
+    }
+
+    value
+        .parse::<IpNet>()
+        .map(|net| net.trunc())
+        .map_err(|_| format!("'{}' is neither an IP address nor a CIDR range", value))
+}
+
+/// A single port like "8080", or an inclusive range like "6000-6999"
+pub fn parse_port_range(value: &str) -> Result<RangeInclusive<u16>, String> {
+    let value = value.trim();
+    let (start, end) = value.split_once('-').unwrap_or((value, value));
+    match (start.trim().parse::<u16>(), end.trim().parse::<u16>()) {
+        (Ok(start), Ok(end)) if start <= end && start > 0 => Ok(start..=end),
+        _ => Err(format!("'{}' is not a port or a range of ports like 8000-8999", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(hosts: (&[&str], &[&str]), ip_ranges: (&[&str], &[&str]), ports: (&[&str], &[&str])) -> TargetPolicy {
+        let list = |items: &[&str]| items.iter().map(|item| item.to_string()).collect();
+        TargetPolicy::new(&ISecurity {
+            secret_key: String::new(),
+            metrics_token: String::new(),
+            admin_token: String::new(),
+            block_non_global_ips: true,
+            allowed_hosts: list(hosts.0),
+            denied_hosts: list(hosts.1),
+            allowed_ip_ranges: list(ip_ranges.0),
+            denied_ip_ranges: list(ip_ranges.1),
+            allowed_ports: list(ports.0),
+            denied_ports: list(ports.1),
+        })
+    }
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn allowed_hosts_override_denied_hosts() {
+        let policy = policy((&["status.example.com"], &["*.example.com"]), (&[], &[]), (&[], &[]));
+        assert!(policy.denies_host("www.example.com"));
+        assert!(!policy.denies_host("status.example.com"));
+        assert!(!policy.denies_host("example.com"));
+        assert!(!policy.denies_url(&url("https://status.example.com/")));
+        assert!(policy.denies_url(&url("https://cdn.example.com/image.png")));
+    }
+
+    #[test]
+    fn allowed_ip_ranges_override_denied_ip_ranges() {
+        let policy = policy((&[], &[]), (&["10.1.2.3", "fd00::2"], &["10.0.0.0/8", "fd00::/8"]), (&[], &[]));
+        assert!(policy.denies_ip(&ip("10.1.2.4")));
+        assert!(!policy.denies_ip(&ip("10.1.2.3")));
+        assert!(!policy.denies_ip(&ip("11.0.0.1")));
+        assert!(policy.denies_ip(&ip("fd00::3")));
+        assert!(!policy.denies_ip(&ip("fd00::2")));
+        assert!(policy.denies_url(&url("http://[fd00::3]/")));
+        assert!(!policy.denies_url(&url("http://[fd00::2]/")));
+        assert!(policy.denies_url(&url("http://10.9.9.9:8080/")));
+    }
+
+    #[test]
+    fn port_ranges_are_inclusive() {
+        let policy = policy((&[], &[]), (&[], &[]), (&["8080"], &["8000-8999", "22"]));
+        assert!(!policy.denies_port(7999));
+        assert!(policy.denies_port(8000));
+        assert!(policy.denies_port(8999));
+        assert!(!policy.denies_port(9000));
+        assert!(!policy.denies_port(8080));
+        assert!(policy.denies_port(22));
+        assert!(policy.denies_url(&url("http://example.com:8443/")));
+        assert!(!policy.denies_url(&url("http://example.com/")));
+    }
+
+    #[test]
+    fn parses_port_ranges() {
+        assert_eq!(parse_port_range("80"), Ok(80..=80));
+        assert_eq!(parse_port_range(" 8000 - 8999 "), Ok(8000..=8999));
+        assert_eq!(parse_port_range("65535"), Ok(65535..=65535));
+        assert!(parse_port_range("0").is_err());
+        assert!(parse_port_range("65536").is_err());
+        assert!(parse_port_range("9000-8000").is_err());
+        assert!(parse_port_range("http").is_err());
+    }
+
+    #[test]
+    fn parses_ip_ranges() {
+        assert_eq!(parse_ip_range("10.1.2.3"), Ok("10.1.2.3/32".parse().unwrap()));
+        assert_eq!(parse_ip_range("::1"), Ok("::1/128".parse().unwrap()));
+        assert_eq!(parse_ip_range("10.1.2.3/8"), Ok("10.0.0.0/8".parse().unwrap()));
+        assert!(parse_ip_range("10.0.0.0/33").is_err());
+        assert!(parse_ip_range("example.com").is_err());
+    }
+}
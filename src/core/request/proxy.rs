@@ -0,0 +1,80 @@
+use crate::{config::IProxy, core::request::pattern::HostPattern};
+use reqwest::Proxy;
+use std::sync::Arc;
+use url::Url;
+
+// Value of a rule's proxy that sends the matching hosts out directly
+const DIRECT: &str = "direct";
+
+/// Which proxy, if any, a request goes through according to `[proxy]`
+#[derive(Debug, Default)]
+pub struct ProxyRoutes {
+    default: Option<Url>,
+    no_proxy: Vec<HostPattern>,
+    // `None` for rules that exempt their hosts from the default proxy
+    rules: Vec<(Vec<HostPattern>, Option<Url>)>,
+}
+
+impl ProxyRoutes {
+    /// Invalid entries are left out, they are reported when the config is validated
+    pub fn new(config: &IProxy) -> Self {
+        Self {
+            default: parse_proxy_url(&config.default).ok().flatten(),
+            no_proxy: HostPattern::parse_all(&config.no_proxy),
+            rules: config
+                .rules
+                .iter()
+                .filter_map(|rule| Some((HostPattern::parse_all(&rule.hosts), parse_proxy_url(&rule.proxy).ok()?)))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.rules.iter().all(|(_, proxy)| proxy.is_none())
+    }
+
+    /// The no-proxy list comes first, then the first rule matching the host, then the default proxy
+    pub fn route(&self, url: &Url) -> Option<&Url> {
+        let host = url.host_str()?;
+        if self.no_proxy.iter().any(|p| p.matches(host)) {
+            return None;
+        }
+
+        match self.rules.iter().find(|(hosts, _)| hosts.iter().any(|p| p.matches(host))) {
+            Some((_, proxy)) => proxy.as_ref(),
+            None => self.default.as_ref(),
+        }
+    }
+
+    /// Host names of the proxies themselves
+    pub fn hosts(&self) -> Vec<String> {
+        let proxies = self.default.iter().chain(self.rules.iter().filter_map(|(_, proxy)| proxy.as_ref()));
+        proxies.filter_map(|proxy| proxy.host_str()).map(|host| host.to_string()).collect()
+    }
+}
+
+/// An empty value and `direct` stand for no proxy
+pub fn parse_proxy_url(value: &str) -> Result<Option<Url>, String> {
+    let value = value.trim();
+    if value.is_empty() || value.eq_ignore_ascii_case(DIRECT) {
+        return Ok(None);
+    }
+
+    let url = Url::parse(value).map_err(|e| format!("invalid proxy URL '{}': {}", value, e))?;
+    if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(format!(
+            "unsupported proxy scheme '{}', expected one of http, https, socks5, socks5h",
+            url.scheme()
+        ));
+    }
+    if url.host_str().is_none() {
+        return Err(format!("proxy URL '{}' has no host", value));
+    }
+
+    Ok(Some(url))
+}
+
+/// Credentials in the proxy URL are sent to the proxy by reqwest
+pub fn proxy(routes: Arc<ProxyRoutes>) -> Proxy {
+    Proxy::custom(move |url| routes.route(url).cloned())
+}
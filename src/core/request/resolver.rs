@@ -1,8 +1,12 @@
+use crate::core::request::pattern::HostPattern;
 use anyhow::Result;
 use hickory_resolver::{TokioResolver, config::LookupIpStrategy, lookup_ip::LookupIpIntoIter};
+use http_acl_reqwest::HttpAcl;
 use once_cell::sync::OnceCell;
 use reqwest::dns::{Addrs, Name, Resolve, Resolving};
-use std::{net::SocketAddr, sync::Arc};
+use std::{collections::HashMap, error::Error as StdError, net::SocketAddr, str::FromStr, sync::Arc};
+use thiserror::Error;
+use tokio::sync::OnceCell as AsyncOnceCell;
 
 #[derive(Debug, Default, Clone)]
 pub struct CustomDnsResolver {
@@ -34,8 +38,117 @@ impl Resolve for CustomDnsResolver {
     }
 }
 
-fn create_resolver() -> Result<TokioResolver> {
+pub fn create_resolver() -> Result<TokioResolver> {
     let mut builder = TokioResolver::builder_tokio()?;
     builder.options_mut().ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
     Ok(builder.build())
 }
+
+/// Returned by `AclResolver` when every address of a name is denied, so that it can be told apart from a failed lookup
+#[derive(Error, Debug)]
+#[error("every address of '{0}' is blocked by the access control list")]
+pub struct AclDenied(pub String);
+
+/// Leaves out the resolved addresses that are denied by the ACL, so that a connection can only be made to an allowed one
+pub struct AclResolver {
+    acl: Arc<HttpAcl>,
+    inner: Arc<CustomDnsResolver>,
+}
+
+impl AclResolver {
+    pub fn new(acl: Arc<HttpAcl>, inner: Arc<CustomDnsResolver>) -> Self {
+        Self { acl, inner }
+    }
+}
+
+impl Resolve for AclResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let acl = self.acl.clone();
+        let host = name.as_str().to_string();
+        let lookup = self.inner.resolve(name);
+        Box::pin(async move {
+            let allowed: Vec<SocketAddr> = lookup.await?.filter(|addr| !acl.is_ip_allowed(&addr.ip()).is_denied()).collect();
+            if allowed.is_empty() {
+                return Err(Box::new(AclDenied(host)) as _);
+            }
+
+            let addrs: Addrs = Box::new(allowed.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Addresses of the proxies, looked up once for the lifetime of the client and connected to without the ACL,
+/// which would reject a proxy on the private network the same way as a destination there.
+/// The resolver cannot tell a connection to the proxy from a direct request to the same name,
+/// so `HttpClient::check_target` applies the ACL to these addresses when the proxy is requested directly.
+pub struct PinnedHosts {
+    hosts: HashMap<String, AsyncOnceCell<Vec<SocketAddr>>>,
+    inner: Arc<CustomDnsResolver>,
+}
+
+impl PinnedHosts {
+    pub fn new(hosts: Vec<String>, inner: Arc<CustomDnsResolver>) -> Self {
+        Self {
+            hosts: hosts.into_iter().map(|host| (host.to_ascii_lowercase(), AsyncOnceCell::new())).collect(),
+            inner,
+        }
+    }
+
+    pub fn contains(&self, host: &str) -> bool {
+        self.hosts.contains_key(&host.to_ascii_lowercase())
+    }
+
+    /// A failed lookup is not remembered, the next request tries again
+    pub async fn lookup(&self, host: &str) -> Result<Vec<SocketAddr>, Box<dyn StdError + Send + Sync>> {
+        let Some(cell) = self.hosts.get(&host.to_ascii_lowercase()) else {
+            return Err(format!("'{}' is not a pinned host", host).into());
+        };
+
+        let addrs = cell
+            .get_or_try_init(|| async {
+                let addrs = self.inner.resolve(Name::from_str(host)?).await?;
+                Ok::<_, Box<dyn StdError + Send + Sync>>(addrs.collect())
+            })
+            .await?;
+        Ok(addrs.clone())
+    }
+}
+
+/// Resolves the proxies to their pinned addresses and the allowed hosts of `[security]` without the ACL.
+/// Every other name goes through the ACL, including destinations of socks5 proxies, which reqwest resolves locally.
+pub struct TrustedHostsResolver<R> {
+    acl: Arc<R>,
+    direct: Arc<CustomDnsResolver>,
+    pinned: Arc<PinnedHosts>,
+    trusted: Vec<HostPattern>,
+}
+
+impl<R> TrustedHostsResolver<R> {
+    pub fn new(acl: Arc<R>, direct: Arc<CustomDnsResolver>, pinned: Arc<PinnedHosts>, trusted: Vec<HostPattern>) -> Self {
+        Self {
+            acl,
+            direct,
+            pinned,
+            trusted,
+        }
+    }
+}
+
+impl<R: Resolve> Resolve for TrustedHostsResolver<R> {
+    fn resolve(&self, name: Name) -> Resolving {
+        if self.pinned.contains(name.as_str()) {
+            let pinned = self.pinned.clone();
+            return Box::pin(async move {
+                let addrs: Addrs = Box::new(pinned.lookup(name.as_str()).await?.into_iter());
+                Ok(addrs)
+            });
+        }
+
+        if self.trusted.iter().any(|pattern| pattern.matches(name.as_str())) {
+            self.direct.resolve(name)
+        } else {
+            self.acl.resolve(name)
+        }
+    }
+}
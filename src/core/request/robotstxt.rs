@@ -1,5 +1,5 @@
 use crate::core::{
-    cache,
+    cache, metrics,
     request::{self, RequestOptions},
 };
 use reqwest::StatusCode;
@@ -10,6 +10,8 @@ struct RobotsTxt {
     cached: bool,
     failed: bool,
     disallowed: bool,
+    /// The failure may go away on retry, so the allow-all fallback is not persisted
+    transient: bool,
 }
 
 impl RobotsTxt {
@@ -19,6 +21,14 @@ impl RobotsTxt {
             cached: false,
             failed: true,
             disallowed,
+            transient: false,
+        }
+    }
+
+    fn transient() -> Self {
+        Self {
+            transient: true,
+            ..Self::failed(false)
         }
     }
 
@@ -28,11 +38,20 @@ impl RobotsTxt {
             cached,
             failed: false,
             disallowed: false,
+            transient: false,
         }
     }
 }
 
 pub async fn is_allowed_scraping(url: &Url) -> bool {
+    let allowed = check(url).await;
+
+    let decision = if allowed { "allow" } else { "deny" };
+    metrics::ROBOTS_DECISIONS_TOTAL.with_label_values(&[decision]).inc();
+    allowed
+}
+
+async fn check(url: &Url) -> bool {
     let domain = match url.host_str() {
         Some(d) => d,
         None => return false,
@@ -40,8 +59,8 @@ pub async fn is_allowed_scraping(url: &Url) -> bool {
 
     let result = fetch(domain, url).await;
     if result.failed || result.content.is_none() {
-        if !result.disallowed {
-            cache::set_robotstxt_cache(domain, "");
+        if !result.disallowed && !result.transient {
+            cache::set_robotstxt_cache(domain, "").await;
         }
 
         return !result.disallowed;
@@ -52,14 +71,14 @@ pub async fn is_allowed_scraping(url: &Url) -> bool {
 
     if !result.cached {
         let x = if parsed.is_ok() { txt } else { "".to_string() };
-        cache::set_robotstxt_cache(domain, &x);
+        cache::set_robotstxt_cache(domain, &x).await;
     }
 
     parsed.map_or(true, |robot| robot.allowed(url.path()))
 }
 
 async fn fetch(domain: &str, url: &Url) -> RobotsTxt {
-    if let Some(cached) = cache::get_robotstxt_cache(domain) {
+    if let Some(cached) = cache::get_robotstxt_cache(domain).await {
         tracing::debug!("Robots.txt cache hit for domain: {}", domain);
         return RobotsTxt::success(cached, true);
     }
@@ -74,6 +93,10 @@ async fn fetch(domain: &str, url: &Url) -> RobotsTxt {
 
     let response = match request::get(robots_url.as_str(), &RequestOptions::default()).await {
         Ok(resp) => resp,
+        Err(e) if e.is_transient() => {
+            tracing::debug!("Failed to fetch robots.txt from '{}', not caching the result: {}", robots_url, e);
+            return RobotsTxt::transient();
+        }
         Err(e) => {
             tracing::debug!("Failed to fetch robots.txt from '{}': {}", robots_url, e);
             return RobotsTxt::failed(false);
@@ -92,9 +115,13 @@ async fn fetch(domain: &str, url: &Url) -> RobotsTxt {
     }
 
     let content = match response.text().await {
-        Some(x) => x,
-        None => {
-            tracing::debug!("Failed to read robots.txt content from '{}'", robots_url);
+        Ok(x) => x,
+        Err(e) if e.is_transient() => {
+            tracing::debug!("Failed to read robots.txt content from '{}', not caching the result: {}", robots_url, e);
+            return RobotsTxt::transient();
+        }
+        Err(e) => {
+            tracing::debug!("Failed to read robots.txt content from '{}': {}", robots_url, e);
             return RobotsTxt::failed(false);
         }
     };
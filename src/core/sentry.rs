@@ -3,7 +3,7 @@ use once_cell::sync::Lazy;
 use sentry::types::Dsn;
 
 pub static SENTRY_DSN: Lazy<Option<Dsn>> = Lazy::new(|| {
-    let conf = &config::CONFIG;
+    let conf = config::CONFIG.load();
     if let Some(sentry) = &conf.sentry &&
         let Some(dsn) = &sentry.dsn &&
         let Ok(sentry_dsn) = sentry::IntoDsn::into_dsn(dsn.clone())
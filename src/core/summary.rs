@@ -1,74 +1,316 @@
 use crate::{
     config::CONFIG,
-    core::{cache, summary::def::SummarizeArguments},
+    core::{
+        cache,
+        error::SummaryError,
+        metrics,
+        request::{self, RequestOptions, Validators},
+        summary::def::{SummarizeArguments, SummaryOutcome},
+    },
 };
 use language_tags::LanguageTag;
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio_util::task::TaskTracker;
+use url::Url;
 
+pub mod admin;
+pub mod canonicalize;
+mod coalesce;
 pub mod def;
 pub mod handler;
+pub mod media;
 pub mod selector;
 pub mod summarize;
 pub mod utility;
 
-static ACTIVE_HANDLERS: Lazy<Vec<&'static dyn def::SummalyHandler>> = Lazy::new(|| {
+// Background refreshes of stale summaries, waited for on shutdown so that their results reach the cache before it is closed
+static REFRESHES: Lazy<TaskTracker> = Lazy::new(TaskTracker::new);
+
+/// Waits for the background refreshes that are still running
+pub async fn drain_refreshes() {
+    REFRESHES.close();
+    REFRESHES.wait().await;
+}
+
+/// Handlers in the order they are tried, looked up on every summarization so that reloading the config takes effect right away
+fn active_handlers() -> Vec<&'static dyn def::SummalyHandler> {
+    let config = CONFIG.load();
     let mut handlers: Vec<&dyn def::SummalyHandler> = handler::CUSTOM_HANDLERS
         .iter()
-        .filter(|handler| !CONFIG.plugins.disabled.contains(&handler.id().to_string()))
+        .filter(|handler| !config.plugins.disabled.contains(&handler.id().to_string()))
         .copied()
         .collect();
 
     handlers.push(handler::DEFAULT_HANDLER);
     handlers
-});
+}
 
-pub async fn summary(args: SummarizeArguments) -> Option<def::SummaryResult> {
-    let url = &args.url;
-    let mut lang = args.lang.clone();
+/// Cached value of a summarization, failures are cached as well so that they are not retried on every request.
+/// A summary is fresh until `stale_at`, after that it is still served until `expires_at` while being refreshed in the background.
+#[derive(Serialize, Deserialize)]
+struct CachedSummary {
+    #[serde(flatten)]
+    result: CachedResult,
+    meta: CacheMetadata,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum CachedResult {
+    Summary(Box<def::SummaryResult>),
+    Error(SummaryError),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheMetadata {
+    url: String,
+    lang: Option<String>,
+    handler: String,
+    upstream_status: Option<u16>,
+    fetched_at: u64, // unix timestamp in seconds
+    ttl: u64,        // freshness lifetime in seconds
+    stale_at: u64,   // unix timestamp in seconds
+    expires_at: u64, // unix timestamp in seconds
+    validators: Option<Validators>,
+}
 
-    if let Some(ref l) = lang {
-        let parsed_tag = LanguageTag::parse(l);
-        if parsed_tag.is_err() {
-            tracing::error!("Invalid language code: {}", l);
-            return None;
+impl CacheMetadata {
+    fn new(url: &Url, lang: Option<String>, handler: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            lang,
+            handler: handler.to_string(),
+            upstream_status: None,
+            fetched_at: unix_now(),
+            ttl: 0,
+            stale_at: 0,
+            expires_at: 0,
+            validators: None,
         }
+    }
+}
+
+impl CachedSummary {
+    fn new(outcome: &SummaryOutcome, mut meta: CacheMetadata) -> Option<Self> {
+        let (result, stale_ttl) = match &outcome.result {
+            Ok(summary) => (CachedResult::Summary(Box::new(summary.clone())), CONFIG.load().cache.stale_ttl),
+            // Failures are never served stale
+            Err(error) => (CachedResult::Error(error.clone()), 0),
+        };
+
+        // Without a freshness lifetime the summary would only be served stale
+        meta.ttl = outcome.ttl.filter(|ttl| *ttl > 0)?;
+        meta.stale_at = meta.fetched_at + meta.ttl;
+        meta.expires_at = meta.stale_at + stale_ttl;
+
+        Some(Self { result, meta })
+    }
+
+    fn parse(cached: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(cached).ok().filter(|c| c.meta.expires_at > unix_now())
+    }
 
-        let mut tag = parsed_tag.unwrap().into_string();
-        if tag == "ja-KS" {
-            tag = "ja-JP".to_string();
+    fn is_stale(&self) -> bool {
+        self.meta.stale_at <= unix_now()
+    }
+
+    fn is_summary(&self) -> bool {
+        matches!(self.result, CachedResult::Summary(_))
+    }
+
+    fn outcome(&self) -> SummaryOutcome {
+        let result = match &self.result {
+            CachedResult::Summary(summary) => Ok(*summary.clone()),
+            CachedResult::Error(error) => Err(error.clone()),
+        };
+
+        SummaryOutcome {
+            result,
+            ttl: Some(self.meta.stale_at.saturating_sub(unix_now())),
         }
+    }
+
+    /// Starts the lifetime over, used when upstream confirmed that the content has not changed
+    fn extend(mut self) -> Self {
+        let stale_ttl = self.meta.expires_at - self.meta.stale_at;
+        self.meta.fetched_at = unix_now();
+        self.meta.stale_at = self.meta.fetched_at + self.meta.ttl;
+        self.meta.expires_at = self.meta.stale_at + stale_ttl;
+        self
+    }
+
+    async fn save(&self, url: &str, lang: Option<String>, variant: Option<&str>) {
+        if let Ok(serialized) = serde_json::to_string(self) {
+            let ttl = self.meta.expires_at.saturating_sub(unix_now());
+            cache::set_summarize_cache(url, lang, variant, &serialized, &ttl).await;
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+fn normalize_lang(lang: Option<String>) -> Result<Option<String>, SummaryError> {
+    let Some(l) = lang else { return Ok(None) };
+
+    let parsed_tag = LanguageTag::parse(&l);
+    if parsed_tag.is_err() {
+        tracing::error!("Invalid language code: {}", l);
+        return Err(SummaryError::InvalidLanguage(l));
+    }
 
-        lang = Some(tag);
+    let mut tag = parsed_tag.unwrap().into_string();
+    if tag == "ja-KS" {
+        tag = "ja-JP".to_string();
     }
 
-    let cache = cache::get_summarize_cache(url.as_str(), lang.clone());
-    if let Some(cached) = cache {
+    Ok(Some(tag))
+}
+
+/// Rejects URLs denied by `[security]` before anything is fetched or served from the cache
+fn check_target(url: &Url) -> Result<(), SummaryError> {
+    if request::is_denied_target(url) {
+        tracing::info!("'{}' is denied by the security policy", url);
+        return Err(SummaryError::TargetDenied);
+    }
+
+    Ok(())
+}
+
+pub async fn summary(mut args: SummarizeArguments) -> SummaryOutcome {
+    args.url = canonicalize::canonicalize(&args.url);
+    let url = &args.url;
+    let lang = match normalize_lang(args.lang.clone()) {
+        Ok(l) => l,
+        Err(error) => return SummaryOutcome::uncacheable(error),
+    };
+
+    // Checked before the cache, so that denying a host also stops serving the summaries cached before
+    if let Err(error) = check_target(url) {
+        return SummaryOutcome::uncacheable(error);
+    }
+
+    // Results fetched with other limits than the defaults are kept apart, the caller's own limits may have caused them
+    let variant = RequestOptions::from(&args).variant();
+    let key = cache::summarize_key(url.as_str(), lang.clone(), variant.as_deref());
+    let cached = cache::get_summarize_cache(url.as_str(), lang.clone(), variant.as_deref()).await;
+
+    if let Some(cached) = cached.as_deref().and_then(CachedSummary::parse) {
         tracing::debug!("Cache hit for URL: {}", url);
-        return serde_json::from_str(&cached).ok();
+        let outcome = cached.outcome();
+
+        // A refresh that is already running for this key is left alone
+        if cached.is_stale() && !coalesce::is_running(&key) {
+            tracing::debug!("Serving stale summary for {} while refreshing it in the background", url);
+            REFRESHES.spawn(coalesce::run(key, fetch(args, lang, variant, Some(cached))));
+        }
+
+        return outcome;
     }
 
-    for handler in ACTIVE_HANDLERS.iter() {
+    coalesce::run(key, fetch(args, lang, variant, None)).await
+}
+
+async fn fetch(mut args: SummarizeArguments, lang: Option<String>, variant: Option<String>, previous: Option<CachedSummary>) -> SummaryOutcome {
+    let url = args.url.clone();
+
+    let lock = cache::lock_summarize(url.as_str(), lang.clone(), variant.as_deref()).await;
+    if lock.is_none() {
+        tracing::debug!("Another instance is already summarizing {}, waiting for its result", url);
+        if let Some(cached) = cache::wait_summarize_cache(url.as_str(), lang.clone(), variant.as_deref())
+            .await
+            .as_deref()
+            .and_then(CachedSummary::parse)
+        {
+            return cached.outcome();
+        }
+    }
+
+    args.validators = previous.as_ref().and_then(|p| p.meta.validators.clone());
+    let summary = run_handler(&args, lang, variant.as_deref(), previous).await;
+
+    if let Some(lock) = lock {
+        cache::unlock_summarize(lock).await;
+    }
+
+    summary
+}
+
+async fn run_handler(args: &SummarizeArguments, lang: Option<String>, variant: Option<&str>, previous: Option<CachedSummary>) -> SummaryOutcome {
+    let url = &args.url;
+
+    for handler in active_handlers() {
         if handler.test(url) {
             tracing::debug!("Using handler: {}", handler.id());
 
-            let summary = match handler.summarize(&args).await {
-                Some(mut s) => {
+            let result = handler.summarize(args).await;
+            if let Err(SummaryError::NotModified) = result &&
+                let Some(previous) = previous
+            {
+                tracing::debug!("{} has not been modified, extending the cached summary", url);
+                let extended = previous.extend();
+                extended.save(url.as_str(), lang, variant).await;
+                return extended.outcome();
+            }
+
+            let mut meta = CacheMetadata::new(url, lang.clone(), handler.id());
+            let (summary, ttl) = match result {
+                Ok(mut s) => {
                     if s.summary.url.is_none() {
                         s.summary.url = Some(url.as_str().to_string());
                     }
 
-                    let serialized = serde_json::to_string(&s.summary).ok()?;
-                    cache::set_summarize_cache(url.as_str(), lang.clone(), &serialized, &s.cache_ttl.clamp(300, 86400));
-                    Some(s.summary)
+                    // A response that is already stale, like one with max-age=0, is not kept for min_ttl
+                    let config = CONFIG.load();
+                    let ttl = s.cache_ttl.map(|ttl| match ttl {
+                        0 => 0,
+                        ttl => ttl.clamp(config.cache.min_ttl, config.cache.max_ttl.max(config.cache.min_ttl)),
+                    });
+
+                    meta.upstream_status = s.upstream_status;
+                    meta.validators = s.validators;
+                    (Ok(s.summary), ttl)
                 }
-                None => {
-                    cache::set_summarize_cache(url.as_str(), lang.clone(), "null", &300);
-                    None
+                Err(error) => {
+                    tracing::debug!("Handler {} failed for {}: {}", handler.id(), url, error);
+                    if let SummaryError::UpstreamStatus(status) = error {
+                        meta.upstream_status = Some(status);
+                    }
+
+                    // Denials are not stored, the policy may change with the next reload
+                    let config = CONFIG.load();
+                    let ttl = match error {
+                        SummaryError::TargetDenied => None,
+                        _ if error.is_transient() => Some(config.cache.transient_error_ttl),
+                        _ => Some(config.cache.permanent_error_ttl),
+                    };
+
+                    (Err(error), ttl)
                 }
             };
 
-            return summary;
+            let label = summary.as_ref().map_or_else(|e| e.code(), |_| "success");
+            metrics::SUMMARIZE_TOTAL.with_label_values(&[handler.id(), label]).inc();
+
+            // A failed refresh keeps serving the stale summary until it expires for good
+            if summary.is_err() &&
+                let Some(previous) = previous.filter(CachedSummary::is_summary)
+            {
+                tracing::debug!("Failed to refresh {}, keeping the stale summary", url);
+                return previous.outcome();
+            }
+
+            let outcome = SummaryOutcome { result: summary, ttl };
+            match CachedSummary::new(&outcome, meta) {
+                Some(cached) => cached.save(url.as_str(), lang, variant).await,
+                None => tracing::debug!("Result for {} must not be stored, skipping cache", url),
+            }
+
+            return outcome;
         }
     }
-    None
+    SummaryOutcome::uncacheable(SummaryError::UnsupportedUrl)
 }
@@ -0,0 +1,56 @@
+use crate::core::{
+    cache,
+    error::SummaryError,
+    summary::{CachedSummary, canonicalize, check_target, coalesce, def::SummarizeArguments, fetch, normalize_lang},
+};
+use url::Url;
+
+/// Applies the same canonicalization as `summary()` so that the entry for the URL is found
+fn identify(url: &Url, lang: Option<String>) -> Result<(Url, Option<String>), SummaryError> {
+    Ok((canonicalize::canonicalize(url), normalize_lang(lang)?))
+}
+
+/// Returns the cache envelope for a URL, including the metadata of the entry
+pub async fn lookup(url: &Url, lang: Option<String>) -> Result<Option<serde_json::Value>, SummaryError> {
+    let (url, lang) = identify(url, lang)?;
+    let cached = cache::get_summarize_cache(url.as_str(), lang, None).await;
+
+    Ok(cached
+        .as_deref()
+        .and_then(CachedSummary::parse)
+        .and_then(|c| serde_json::to_value(c).ok()))
+}
+
+pub async fn delete(url: &Url, lang: Option<String>) -> Result<bool, SummaryError> {
+    let (url, lang) = identify(url, lang)?;
+    Ok(cache::delete_summarize_cache(url.as_str(), lang, None).await)
+}
+
+/// Summarizes the URL again regardless of the cached entry, which is replaced by the result
+pub async fn refresh(mut args: SummarizeArguments) -> Result<Option<serde_json::Value>, SummaryError> {
+    let (url, lang) = identify(&args.url, args.lang.clone())?;
+    args.url = url.clone();
+    check_target(&url)?;
+
+    let key = cache::summarize_key(url.as_str(), lang.clone(), None);
+    coalesce::run(key, fetch(args, lang.clone(), None, None)).await;
+
+    lookup(&url, lang).await
+}
+
+/// Deletes every entry produced by the handler, returns the number of deleted entries
+pub async fn purge_handler(handler_id: &str) -> usize {
+    let mut deleted = 0;
+
+    for (key, value) in cache::scan_summarize_cache().await {
+        let Ok(cached) = serde_json::from_str::<CachedSummary>(&value) else {
+            continue;
+        };
+        if cached.meta.handler == handler_id && cache::delete_cache_key(&key).await {
+            deleted += 1;
+        }
+    }
+
+    tracing::info!("Purged {} cache entries produced by handler {}", deleted, handler_id);
+    deleted
+}
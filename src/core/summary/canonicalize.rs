@@ -0,0 +1,199 @@
+use crate::config::{CONFIG, ICanonicalize, ICanonicalizeRule};
+use url::Url;
+
+fn matches_domain(host: &str, domain: &str) -> bool {
+    host == domain || host.strip_suffix(domain).is_some_and(|sub| sub.ends_with('.'))
+}
+
+/// Whether a URL taken from a page may stand for the page at `url`, which is only the case on the same host and between a
+/// host and its `www.` variant. Parent domains are not accepted, they may be public suffixes shared by unrelated sites.
+pub fn is_same_site(url: &Url, other: &Url) -> bool {
+    match (url.domain(), other.domain()) {
+        (Some(a), Some(b)) => a == b || a.strip_prefix("www.") == Some(b) || b.strip_prefix("www.") == Some(a),
+        _ => url.host() == other.host(),
+    }
+}
+
+/// Parameter patterns may end with `*` to match every parameter with that prefix, e.g. `utm_*`
+fn matches_param(key: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => key.len() >= prefix.len() && key[..prefix.len()].eq_ignore_ascii_case(prefix),
+        None => key.eq_ignore_ascii_case(pattern),
+    }
+}
+
+fn is_kept(key: &str, conf: &ICanonicalize, rules: &[&ICanonicalizeRule]) -> bool {
+    if conf.strip_params.iter().any(|p| matches_param(key, p)) {
+        return false;
+    }
+
+    rules.iter().all(|rule| {
+        let allowed = rule.keep_params.is_empty() || rule.keep_params.iter().any(|p| matches_param(key, p));
+        allowed && !rule.strip_params.iter().any(|p| matches_param(key, p))
+    })
+}
+
+/// Rewrites a URL into the form used for fetching and as the cache key, so that trivially different URLs share one entry
+pub fn canonicalize(url: &Url) -> Url {
+    canonicalize_with(url, &CONFIG.load().canonicalize)
+}
+
+fn canonicalize_with(url: &Url, conf: &ICanonicalize) -> Url {
+    if !conf.enabled {
+        return url.clone();
+    }
+
+    let mut canonical = url.clone();
+    let host = url.host_str().unwrap_or_default().to_string();
+    let rules = conf.rules.iter().filter(|r| matches_domain(&host, &r.domain)).collect::<Vec<_>>();
+
+    if conf.drop_fragment {
+        canonical.set_fragment(None);
+    }
+
+    if rules.iter().any(|r| r.strip_www) &&
+        let Some(stripped) = host.strip_prefix("www.") &&
+        canonical.set_host(Some(stripped)).is_err()
+    {
+        tracing::debug!("Failed to strip www. from {}", url);
+    }
+
+    if rules.iter().any(|r| r.strip_trailing_slash) && canonical.path().len() > 1 && canonical.path().ends_with('/') {
+        let path = canonical.path().trim_end_matches('/').to_string();
+        canonical.set_path(&path);
+    }
+
+    // Works on the raw segments so that the encoding and parameters without a value are kept as they are
+    if let Some(query) = url.query() {
+        let mut segments = query
+            .split('&')
+            .filter(|segment| !segment.is_empty())
+            .filter(|segment| {
+                let key = segment.split_once('=').map_or(*segment, |(key, _)| key);
+                let key = urlencoding::decode(key).map_or(key.to_string(), |k| k.into_owned());
+                is_kept(&key, conf, &rules)
+            })
+            .collect::<Vec<_>>();
+
+        if conf.sort_query {
+            segments.sort();
+        }
+
+        canonical.set_query((!segments.is_empty()).then(|| segments.join("&")).as_deref());
+    }
+
+    if canonical != *url {
+        tracing::debug!("Canonicalized {} to {}", url, canonical);
+    }
+
+    canonical
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conf(sort_query: bool, rules: Vec<ICanonicalizeRule>) -> ICanonicalize {
+        ICanonicalize {
+            enabled: true,
+            drop_fragment: true,
+            sort_query,
+            strip_params: vec!["utm_*".to_string(), "fbclid".to_string()],
+            rules,
+        }
+    }
+
+    fn rule(domain: &str) -> ICanonicalizeRule {
+        ICanonicalizeRule {
+            domain: domain.to_string(),
+            strip_www: false,
+            strip_trailing_slash: false,
+            strip_params: vec![],
+            keep_params: vec![],
+        }
+    }
+
+    fn canonical(url: &str, conf: &ICanonicalize) -> String {
+        canonicalize_with(&Url::parse(url).unwrap(), conf).to_string()
+    }
+
+    fn same_site(url: &str, other: &str) -> bool {
+        is_same_site(&Url::parse(url).unwrap(), &Url::parse(other).unwrap())
+    }
+
+    #[test]
+    fn strips_tracking_params() {
+        let conf = conf(false, vec![]);
+        assert_eq!(
+            canonical("https://example.com/a?utm_source=x&id=1&UTM_Medium=y&fbclid=z#top", &conf),
+            "https://example.com/a?id=1"
+        );
+        assert_eq!(canonical("https://example.com/a?utm_source=x", &conf), "https://example.com/a");
+        assert_eq!(
+            canonical("https://example.com/a?utm=x&flag&q=a%20b", &conf),
+            "https://example.com/a?utm=x&flag&q=a%20b"
+        );
+        assert_eq!(
+            canonical("https://example.com/a?%75tm_source=x&id=1", &conf),
+            "https://example.com/a?id=1"
+        );
+    }
+
+    #[test]
+    fn sorts_query_only_if_enabled() {
+        let url = "https://example.com/?b=2&a=1&a=0";
+        assert_eq!(canonical(url, &conf(false, vec![])), "https://example.com/?b=2&a=1&a=0");
+        assert_eq!(canonical(url, &conf(true, vec![])), "https://example.com/?a=0&a=1&b=2");
+    }
+
+    #[test]
+    fn applies_domain_rules() {
+        let strip = conf(
+            false,
+            vec![ICanonicalizeRule {
+                strip_www: true,
+                strip_trailing_slash: true,
+                strip_params: vec!["ref".to_string()],
+                ..rule("example.com")
+            }],
+        );
+        assert_eq!(canonical("https://www.example.com/a/?ref=x&id=1", &strip), "https://example.com/a?id=1");
+        assert_eq!(canonical("https://www.example.com/", &strip), "https://example.com/");
+        assert_eq!(canonical("https://www.example.org/a/?ref=x", &strip), "https://www.example.org/a/?ref=x");
+        assert_eq!(canonical("https://www.notexample.com/a/", &strip), "https://www.notexample.com/a/");
+
+        let keep = conf(
+            false,
+            vec![ICanonicalizeRule {
+                keep_params: vec!["id".to_string()],
+                ..rule("example.com")
+            }],
+        );
+        assert_eq!(canonical("https://sub.example.com/?id=1&page=2", &keep), "https://sub.example.com/?id=1");
+    }
+
+    #[test]
+    fn does_nothing_if_disabled() {
+        let conf = ICanonicalize {
+            enabled: false,
+            ..conf(true, vec![])
+        };
+        assert_eq!(
+            canonical("https://example.com/?utm_source=x#top", &conf),
+            "https://example.com/?utm_source=x#top"
+        );
+    }
+
+    #[test]
+    fn same_site_is_the_host_or_its_www_variant() {
+        assert!(same_site("https://example.com/a", "https://example.com/b"));
+        assert!(same_site("https://example.com/", "http://www.example.com/"));
+        assert!(same_site("https://www.example.com/", "https://example.com/"));
+        assert!(same_site("http://127.0.0.1:8080/", "http://127.0.0.1/"));
+        assert!(!same_site("https://evil.github.io/", "https://github.io/"));
+        assert!(!same_site("https://x.co.jp/", "https://co.jp/"));
+        assert!(!same_site("https://example.com/", "https://blog.example.com/"));
+        assert!(!same_site("https://www.example.com/", "https://www.example.org/"));
+        assert!(!same_site("https://example.com/", "https://127.0.0.1/"));
+    }
+}
@@ -0,0 +1,48 @@
+use crate::core::summary::def::SummaryOutcome;
+use futures::{
+    FutureExt,
+    future::{BoxFuture, Shared},
+};
+use once_cell::sync::Lazy;
+use std::{collections::HashMap, future::Future, sync::Mutex};
+
+type InFlight = Shared<BoxFuture<'static, SummaryOutcome>>;
+
+static IN_FLIGHT: Lazy<Mutex<HashMap<String, InFlight>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Removes the in-flight entry once the caller that started it finishes or is dropped.
+/// Callers that already joined keep their own handle to the shared future and are not affected.
+struct InFlightGuard {
+    key: String,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT.lock().unwrap().remove(&self.key);
+    }
+}
+
+pub fn is_running(key: &str) -> bool {
+    IN_FLIGHT.lock().unwrap().contains_key(key)
+}
+
+/// Runs `future` unless a summarization for the same key is already in progress, in which case its result is shared.
+pub async fn run<F>(key: String, future: F) -> SummaryOutcome
+where F: Future<Output = SummaryOutcome> + Send + 'static {
+    let (shared, _guard) = {
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        match in_flight.get(&key) {
+            Some(shared) => {
+                tracing::debug!("Joining in-flight summarization for key: {}", key);
+                (shared.clone(), None)
+            }
+            None => {
+                let shared = future.boxed().shared();
+                in_flight.insert(key.clone(), shared.clone());
+                (shared, Some(InFlightGuard { key }))
+            }
+        }
+    };
+
+    shared.await
+}
@@ -1,3 +1,7 @@
+use crate::core::{
+    error::SummaryError,
+    request::{Redirect, Validators},
+};
 use async_trait::async_trait;
 use scraper::Html;
 use serde::{Deserialize, Serialize};
@@ -27,19 +31,40 @@ pub struct SummaryResult {
     pub fediverse_creator: Option<String>,
     pub large_card: Option<bool>,
     pub url: Option<String>,
+    /// Redirects followed to reach the summarized page, only included in responses on request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirects: Option<Vec<Redirect>>,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct SummaryResultWithMetadata {
     pub summary: SummaryResult,
-    pub cache_ttl: u64, // in seconds
+    pub cache_ttl: Option<u64>, // in seconds, None if the result must not be cached
+    pub validators: Option<Validators>,
+    pub upstream_status: Option<u16>,
+}
+
+/// Result of `summary()` together with how long it stays fresh
+#[derive(Debug, Clone)]
+pub struct SummaryOutcome {
+    pub result: Result<SummaryResult, SummaryError>,
+    pub ttl: Option<u64>, // remaining lifetime in seconds, None if the result must not be cached
+}
+
+impl SummaryOutcome {
+    pub fn uncacheable(error: SummaryError) -> Self {
+        Self {
+            result: Err(error),
+            ttl: None,
+        }
+    }
 }
 
 #[async_trait]
 pub trait SummalyHandler: Send + Sync {
     fn id(&self) -> &str;
     fn test(&self, url: &Url) -> bool;
-    async fn summarize(&self, args: &SummarizeArguments) -> Option<SummaryResultWithMetadata>;
+    async fn summarize(&self, args: &SummarizeArguments) -> Result<SummaryResultWithMetadata, SummaryError>;
 }
 
 /* Summarize */
@@ -59,17 +84,42 @@ pub trait SummarizeHandler: Send + Sync {
     fn activity_pub(&self, url: &Url, html: &Html) -> Option<String>;
     fn fediverse_creator(&self, url: &Url, html: &Html) -> Option<String>;
     fn summary_large_image(&self, url: &Url, html: &Html) -> bool;
+    fn canonical_url(&self, url: &Url, html: &Html) -> Option<String>;
+
+    /// Whether the handler looks at elements past `<head>`, only then is the whole page downloaded
+    fn reads_body(&self) -> bool {
+        false
+    }
 }
 
 pub struct SummarizeArguments {
     pub url: Url,
     pub lang: Option<String>,
     pub user_agent: Option<String>,
-    // pub follow_redirects: Option<bool>,
-    // pub response_timeout: Option<u64>,
-    // pub operation_timeout: Option<u64>,
-    // pub content_length_limit: Option<usize>,
-    // pub content_length_required: Option<bool>,
+    pub follow_redirects: Option<bool>,
+    pub response_timeout: Option<u64>,
+    pub operation_timeout: Option<u64>,
+    pub content_length_limit: Option<usize>,
+    pub content_length_required: Option<bool>,
+    /// Set when refreshing a cached summary, handlers that support it make a conditional request with these
+    pub validators: Option<Validators>,
+}
+
+impl SummarizeArguments {
+    /// Fetches the URL with the configured defaults
+    pub fn new(url: Url, lang: Option<String>) -> Self {
+        Self {
+            url,
+            lang,
+            user_agent: None,
+            follow_redirects: None,
+            response_timeout: None,
+            operation_timeout: None,
+            content_length_limit: None,
+            content_length_required: None,
+            validators: None,
+        }
+    }
 }
 
 /* oEmbed */
@@ -1,4 +1,5 @@
 use crate::core::{
+    error::SummaryError,
     request::{self, RequestOptions},
     summary::{
         def::{Player, SummalyHandler, SummarizeArguments, SummarizeHandler, SummaryResultWithMetadata},
@@ -33,18 +34,21 @@ impl SummalyHandler for AmazonHandler {
         DOMAIN_REGEX.is_match(host)
     }
 
-    async fn summarize(&self, args: &SummarizeArguments) -> Option<SummaryResultWithMetadata> {
+    async fn summarize(&self, args: &SummarizeArguments) -> Result<SummaryResultWithMetadata, SummaryError> {
         let mut options: RequestOptions = args.into();
         options.user_agent = request::UserAgentList::TwitterBot;
 
-        let response = request::get(args.url.as_str(), &options).await.ok()?;
+        let response = request::get(args.url.as_str(), &options).await?.error_for_status()?;
+        let upstream_status = Some(response.response.status().as_u16());
 
-        let body = response.text().await?;
+        let body = summarize::read_html(response, &AmazonSummarizeHandler).await?;
         let summarized = summarize::execute_summarize(&args.url, body, args, &AmazonSummarizeHandler).await?;
 
-        Some(SummaryResultWithMetadata {
+        Ok(SummaryResultWithMetadata {
             summary: summarized,
-            cache_ttl: 3600,
+            cache_ttl: Some(3600),
+            validators: None,
+            upstream_status,
         })
     }
 }
@@ -93,6 +97,10 @@ impl SummarizeHandler for AmazonSummarizeHandler {
         Some(html.select(&SELECTOR_ID_ADULT_WARNING).next().is_some())
     }
 
+    fn reads_body(&self) -> bool {
+        true
+    }
+
     fn activity_pub(&self, _url: &Url, _html: &Html) -> Option<String> {
         None
     }
@@ -104,4 +112,8 @@ impl SummarizeHandler for AmazonSummarizeHandler {
     fn summary_large_image(&self, _url: &Url, _html: &Html) -> bool {
         true
     }
+
+    fn canonical_url(&self, url: &Url, html: &Html) -> Option<String> {
+        GenericSummarizeHandler.canonical_url(url, html)
+    }
 }
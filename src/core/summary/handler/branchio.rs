@@ -1,8 +1,9 @@
 use crate::core::{
-    request::{self},
+    error::SummaryError,
+    request::{self, RequestOptions},
     summary::{
         def::{SummalyHandler, SummarizeArguments, SummaryResultWithMetadata},
-        summarize,
+        summarize::{self, GenericSummarizeHandler},
     },
 };
 use async_trait::async_trait;
@@ -21,17 +22,24 @@ impl SummalyHandler for BranchioHandler {
         domain == "spotify.link" || domain.ends_with(".app.link")
     }
 
-    async fn summarize(&self, args: &SummarizeArguments) -> Option<SummaryResultWithMetadata> {
+    async fn summarize(&self, args: &SummarizeArguments) -> Result<SummaryResultWithMetadata, SummaryError> {
         let mut fixed_url = args.url.clone();
         fixed_url.set_query(Some("$web_only=true"));
 
-        let response = request::get(fixed_url.as_str(), &args.into()).await.ok()?;
-        let ttl = &response.ttl();
-        let summarized = summarize::generic_summarize(&fixed_url, response.text().await?, args).await?;
+        let mut options: RequestOptions = args.into();
+        options.validators = args.validators.clone();
 
-        Some(SummaryResultWithMetadata {
+        let response = request::get(fixed_url.as_str(), &options).await?.error_for_status()?;
+        let ttl = response.ttl();
+        let validators = response.validators();
+        let upstream_status = Some(response.response.status().as_u16());
+        let summarized = summarize::generic_summarize(&fixed_url, summarize::read_html(response, &GenericSummarizeHandler).await?, args).await?;
+
+        Ok(SummaryResultWithMetadata {
             summary: summarized,
-            cache_ttl: *ttl,
+            cache_ttl: ttl,
+            validators,
+            upstream_status,
         })
     }
 }
@@ -1,10 +1,12 @@
 use crate::{
     config::CONFIG,
     core::{
-        request,
+        error::SummaryError,
+        request::{self, RequestOptions, content::ContentKind},
         summary::{
             def::{SummalyHandler, SummarizeArguments, SummaryResultWithMetadata},
-            summarize,
+            media,
+            summarize::{self, GenericSummarizeHandler},
         },
     },
 };
@@ -23,20 +25,47 @@ impl SummalyHandler for GeneralHandler {
         true
     }
 
-    async fn summarize(&self, args: &SummarizeArguments) -> Option<SummaryResultWithMetadata> {
+    async fn summarize(&self, args: &SummarizeArguments) -> Result<SummaryResultWithMetadata, SummaryError> {
         let url = &args.url;
-        if !&CONFIG.general.ignore_robots_txt && !request::robotstxt::is_allowed_scraping(url).await {
+        let ignore_robots_txt = CONFIG.load().general.ignore_robots_txt;
+        if !ignore_robots_txt && !request::robotstxt::is_allowed_scraping(url).await {
             tracing::info!("Scraping disallowed by robots.txt: {}", url);
-            return None;
+            return Err(SummaryError::RobotsDisallowed);
         }
 
-        let response = request::get(url.as_str(), &args.into()).await.ok()?;
-        let ttl = &response.ttl();
-        let summarized = summarize::generic_summarize(url, response.text().await?, args).await?;
+        let mut options: RequestOptions = args.into();
+        options.validators = args.validators.clone();
+        options.check_robots_txt = !ignore_robots_txt;
 
-        Some(SummaryResultWithMetadata {
+        let mut response = request::get(url.as_str(), &options).await?.error_for_status()?;
+        let content = response.content().await?;
+        if content.kind == ContentKind::Text {
+            let content_type = response.content_type().unwrap_or_default();
+            tracing::debug!("Unsupported content type for '{}': {}", url, content_type);
+            return Err(SummaryError::UnsupportedContentType(content_type));
+        }
+
+        let ttl = response.ttl();
+        let validators = response.validators();
+        let upstream_status = Some(response.response.status().as_u16());
+        // Relative links on the page are relative to where we ended up, not to the requested URL
+        let final_url = response.url().clone();
+        let redirects = response.redirects.clone();
+        let mut summarized = match content.kind {
+            ContentKind::Html => {
+                let html = summarize::read_html(response, &GenericSummarizeHandler).await?;
+                summarize::generic_summarize(&final_url, html, args).await?
+            }
+            _ => media::summarize(&final_url, response, &content).await?,
+        };
+        summarized.url.get_or_insert_with(|| final_url.to_string());
+        summarized.redirects = Some(redirects);
+
+        Ok(SummaryResultWithMetadata {
             summary: summarized,
-            cache_ttl: *ttl,
+            cache_ttl: ttl,
+            validators,
+            upstream_status,
         })
     }
 }
@@ -1,8 +1,9 @@
 use crate::core::{
+    error::SummaryError,
     request::{self, RequestOptions},
     summary::{
         def::{SummalyHandler, SummarizeArguments, SummaryResultWithMetadata},
-        summarize,
+        summarize::{self, GenericSummarizeHandler},
     },
 };
 use async_trait::async_trait;
@@ -21,17 +22,20 @@ impl SummalyHandler for RedditHandler {
         host == "reddit.com" || host.ends_with(".reddit.com") || host == "redd.it"
     }
 
-    async fn summarize(&self, args: &SummarizeArguments) -> Option<SummaryResultWithMetadata> {
+    async fn summarize(&self, args: &SummarizeArguments) -> Result<SummaryResultWithMetadata, SummaryError> {
         let mut options: RequestOptions = args.into();
         options.user_agent = request::UserAgentList::TwitterBot;
 
         let url = &args.url;
-        let response = request::get(url.as_str(), &options).await.ok()?;
-        let summarized = summarize::generic_summarize(url, response.text().await?, args).await?;
+        let response = request::get(url.as_str(), &options).await?.error_for_status()?;
+        let upstream_status = Some(response.response.status().as_u16());
+        let summarized = summarize::generic_summarize(url, summarize::read_html(response, &GenericSummarizeHandler).await?, args).await?;
 
-        Some(SummaryResultWithMetadata {
+        Ok(SummaryResultWithMetadata {
             summary: summarized,
-            cache_ttl: 3600, // 1 hour
+            cache_ttl: Some(3600), // 1 hour
+            validators: None,
+            upstream_status,
         })
     }
 }
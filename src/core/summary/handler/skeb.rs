@@ -1,4 +1,5 @@
 use crate::core::{
+    error::SummaryError,
     request::{self, RequestOptions},
     summary::{
         def::{SummalyHandler, SummarizeArguments, SummaryResult, SummaryResultWithMetadata},
@@ -32,9 +33,9 @@ static REQUEST_OPTIONS: Lazy<RequestOptions> = Lazy::new(|| RequestOptions {
 pub struct SkebHandler;
 
 impl SkebHandler {
-    async fn api_caller<T: DeserializeOwned>(&self, url: &str) -> Option<T> {
-        let u = Url::parse(url).ok()?;
-        let mut response = request::get(url, &REQUEST_OPTIONS).await.ok()?.response;
+    async fn api_caller<T: DeserializeOwned>(&self, url: &str) -> Result<T, SummaryError> {
+        let u = Url::parse(url).map_err(SummaryError::invalid_response)?;
+        let mut response = request::get(url, &REQUEST_OPTIONS).await?.response;
 
         let is_too_many_requests = response.status() == StatusCode::TOO_MANY_REQUESTS;
         let retry_after_zero = response
@@ -46,16 +47,21 @@ impl SkebHandler {
 
         if is_too_many_requests && retry_after_zero {
             tracing::debug!("Skeb cookie check detected, adding cookie...");
-            let body = response.text().await.ok()?;
+            let body = response.text().await.map_err(request::body_error)?;
             let cookie = COOKIE_REGEX
                 .captures(&body)
-                .and_then(|caps| caps.name("cookie").map(|m| m.as_str().to_string()))?;
+                .and_then(|caps| caps.name("cookie").map(|m| m.as_str().to_string()))
+                .ok_or_else(|| SummaryError::invalid_response("cookie not found in the Skeb cookie check"))?;
 
             request::add_cookie(&u, &cookie);
-            response = request::get(url, &REQUEST_OPTIONS).await.ok()?.response;
+            response = request::get(url, &REQUEST_OPTIONS).await?.response;
         }
 
-        response.json::<T>().await.ok()
+        if !response.status().is_success() {
+            return Err(SummaryError::UpstreamStatus(response.status().as_u16()));
+        }
+
+        response.json::<T>().await.map_err(request::body_error)
     }
 }
 
@@ -69,9 +75,9 @@ impl SummalyHandler for SkebHandler {
         ACCEPTABLE_URL_REGEX.is_match(url.as_str())
     }
 
-    async fn summarize(&self, args: &SummarizeArguments) -> Option<SummaryResultWithMetadata> {
-        let caps = ACCEPTABLE_URL_REGEX.captures(args.url.as_str())?;
-        let user = caps.name("user")?.as_str();
+    async fn summarize(&self, args: &SummarizeArguments) -> Result<SummaryResultWithMetadata, SummaryError> {
+        let caps = ACCEPTABLE_URL_REGEX.captures(args.url.as_str()).ok_or(SummaryError::UnsupportedUrl)?;
+        let user = caps.name("user").ok_or(SummaryError::UnsupportedUrl)?.as_str();
         let work = caps.name("work").map(|m| m.as_str());
 
         let summary = if let Some(work_id) = work {
@@ -111,9 +117,11 @@ impl SummalyHandler for SkebHandler {
             ..Default::default()
         };
 
-        Some(SummaryResultWithMetadata {
+        Ok(SummaryResultWithMetadata {
             summary: summarized,
-            cache_ttl: 3600,
+            cache_ttl: Some(3600),
+            validators: None,
+            upstream_status: None,
         })
     }
 }
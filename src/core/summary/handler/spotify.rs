@@ -1,4 +1,5 @@
 use crate::core::{
+    error::SummaryError,
     request::{self, RequestOptions},
     summary::{
         def::{Player, SummalyHandler, SummarizeArguments, SummarizeHandler, SummaryResultWithMetadata},
@@ -21,17 +22,21 @@ impl SummalyHandler for SpotifyHandler {
         url.domain().unwrap_or("") == "open.spotify.com"
     }
 
-    async fn summarize(&self, args: &SummarizeArguments) -> Option<SummaryResultWithMetadata> {
+    async fn summarize(&self, args: &SummarizeArguments) -> Result<SummaryResultWithMetadata, SummaryError> {
         let url = &args.url;
         let mut options: RequestOptions = args.into();
         options.user_agent = request::UserAgentList::TwitterBot;
 
-        let response = request::get(url.as_str(), &options).await.ok()?;
-        let summarized = summarize::execute_summarize(url, response.text().await?, args, &SpotifySummarizeHandler).await?;
+        let response = request::get(url.as_str(), &options).await?.error_for_status()?;
+        let upstream_status = Some(response.response.status().as_u16());
+        let html = summarize::read_html(response, &SpotifySummarizeHandler).await?;
+        let summarized = summarize::execute_summarize(url, html, args, &SpotifySummarizeHandler).await?;
 
-        Some(SummaryResultWithMetadata {
+        Ok(SummaryResultWithMetadata {
             summary: summarized,
-            cache_ttl: 86400, // 1 day
+            cache_ttl: Some(86400), // 1 day
+            validators: None,
+            upstream_status,
         })
     }
 }
@@ -91,4 +96,8 @@ impl SummarizeHandler for SpotifySummarizeHandler {
     fn summary_large_image(&self, _url: &Url, _html: &Html) -> bool {
         false
     }
+
+    fn canonical_url(&self, url: &Url, html: &Html) -> Option<String> {
+        GenericSummarizeHandler.canonical_url(url, html)
+    }
 }
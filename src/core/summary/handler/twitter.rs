@@ -1,4 +1,5 @@
 use crate::core::{
+    error::SummaryError,
     request,
     summary::def::{SummalyHandler, SummarizeArguments, SummaryResult, SummaryResultWithMetadata},
 };
@@ -23,22 +24,24 @@ impl SummalyHandler for TwitterHandler {
         URL_REGEX.is_match(url.as_str())
     }
 
-    async fn summarize(&self, args: &SummarizeArguments) -> Option<SummaryResultWithMetadata> {
+    async fn summarize(&self, args: &SummarizeArguments) -> Result<SummaryResultWithMetadata, SummaryError> {
         let url = &args.url;
-        let id = URL_REGEX.captures(url.as_str())?.name("id")?.as_str();
+        let id = URL_REGEX
+            .captures(url.as_str())
+            .and_then(|caps| caps.name("id"))
+            .ok_or(SummaryError::UnsupportedUrl)?
+            .as_str();
         let response = request::get(
             format!("https://cdn.syndication.twimg.com/tweet-result?id={id}&token=x&lang=en").as_str(),
             &args.into(),
         )
-        .await;
-
-        if response.is_err() {
-            return None;
-        }
-        let response = response.unwrap().text().await;
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
 
         let is_twitter = url.domain().unwrap_or("").to_lowercase().contains("twitter");
-        let tweet = serde_json::from_str::<TweetData>(response.as_ref()?).ok()?;
+        let tweet = serde_json::from_str::<TweetData>(&response).map_err(SummaryError::invalid_response)?;
         let data_available = match &tweet.__typename {
             Some(t) => t == "Tweet",
             None => false,
@@ -62,12 +65,12 @@ impl SummalyHandler for TwitterHandler {
         if data_available {
             let Some(user) = &tweet.user else {
                 tracing::info!("Tweet user data is missing for id: {}", id);
-                return None;
+                return Err(SummaryError::invalid_response("tweet user data is missing"));
             };
 
             let (Some(username), Some(screen_name)) = (&user.name, &user.screen_name) else {
                 tracing::info!("Tweet user name or screen_name is missing for id: {}", id);
-                return None;
+                return Err(SummaryError::invalid_response("tweet user name or screen_name is missing"));
             };
 
             let mut tweet_text = tweet.text.clone().unwrap_or_default();
@@ -100,9 +103,11 @@ impl SummalyHandler for TwitterHandler {
             result.title = if is_twitter { "Twitter".to_string() } else { "X".to_string() };
         }
 
-        Some(SummaryResultWithMetadata {
+        Ok(SummaryResultWithMetadata {
             summary: result,
-            cache_ttl: 3600,
+            cache_ttl: Some(3600),
+            validators: None,
+            upstream_status: None,
         })
     }
 }
@@ -1,4 +1,5 @@
 use crate::core::{
+    error::SummaryError,
     request::{self, RequestOptions},
     summary::{
         def::{SummalyHandler, SummarizeArguments, SummaryResult, SummaryResultWithMetadata},
@@ -27,11 +28,11 @@ impl SummalyHandler for WikipediaHandler {
         host == "wikipedia.org" || host.ends_with(".wikipedia.org")
     }
 
-    async fn summarize(&self, args: &SummarizeArguments) -> Option<SummaryResultWithMetadata> {
+    async fn summarize(&self, args: &SummarizeArguments) -> Result<SummaryResultWithMetadata, SummaryError> {
         let url = &args.url;
-        let caps = PAGE_REGEX.captures(url.as_str())?;
+        let caps = PAGE_REGEX.captures(url.as_str()).ok_or(SummaryError::UnsupportedUrl)?;
         let lang = caps.name("lang").map_or("en", |m| m.as_str());
-        let title = caps.name("title")?.as_str();
+        let title = caps.name("title").ok_or(SummaryError::UnsupportedUrl)?.as_str();
 
         let mut options: RequestOptions = args.into();
         options.accept_mime = Some("application/json".to_string());
@@ -40,20 +41,11 @@ impl SummalyHandler for WikipediaHandler {
             format!("https://{lang}.wikipedia.org/api/rest_v1/page/summary/{title}").as_str(),
             &options,
         )
-        .await;
+        .await?
+        .error_for_status()?;
 
-        let response = match response {
-            Ok(resp) => {
-                if !resp.response.status().is_success() {
-                    return None;
-                }
-                resp
-            }
-            Err(_) => return None,
-        };
-
-        let response = response.text().await;
-        let page_content = serde_json::from_str::<WikipediaApiResponse>(response.as_ref()?).ok()?;
+        let response = response.text().await?;
+        let page_content = serde_json::from_str::<WikipediaApiResponse>(&response).map_err(SummaryError::invalid_response)?;
         let result = SummaryResult {
             title: text_clamp(&page_content.title, 100),
             description: Some(text_clamp(&page_content.extract, 300)),
@@ -63,9 +55,11 @@ impl SummalyHandler for WikipediaHandler {
             ..Default::default()
         };
 
-        Some(SummaryResultWithMetadata {
+        Ok(SummaryResultWithMetadata {
             summary: result,
-            cache_ttl: 604800,
+            cache_ttl: Some(604800),
+            validators: None,
+            upstream_status: None,
         })
     }
 }
@@ -1,8 +1,9 @@
 use crate::core::{
+    error::SummaryError,
     request::{self, RequestOptions},
     summary::{
         def::{SummalyHandler, SummarizeArguments, SummaryResultWithMetadata},
-        summarize,
+        summarize::{self, GenericSummarizeHandler},
     },
 };
 use async_trait::async_trait;
@@ -24,18 +25,23 @@ impl SummalyHandler for YoutubeHandler {
         DOMAIN_REGEX.is_match(url.host_str().unwrap_or(""))
     }
 
-    async fn summarize(&self, args: &SummarizeArguments) -> Option<SummaryResultWithMetadata> {
+    async fn summarize(&self, args: &SummarizeArguments) -> Result<SummaryResultWithMetadata, SummaryError> {
         let mut options: RequestOptions = args.into();
         options.user_agent = request::UserAgentList::TwitterBot;
+        options.validators = args.validators.clone();
 
         let url = &args.url;
-        let response = request::get(url.as_str(), &options).await.ok()?;
-        let ttl = &response.ttl();
-        let summarized = summarize::generic_summarize(url, response.text().await?, args).await?;
+        let response = request::get(url.as_str(), &options).await?.error_for_status()?;
+        let ttl = response.ttl();
+        let validators = response.validators();
+        let upstream_status = Some(response.response.status().as_u16());
+        let summarized = summarize::generic_summarize(url, summarize::read_html(response, &GenericSummarizeHandler).await?, args).await?;
 
-        Some(SummaryResultWithMetadata {
+        Ok(SummaryResultWithMetadata {
             summary: summarized,
-            cache_ttl: *ttl,
+            cache_ttl: ttl,
+            validators,
+            upstream_status,
         })
     }
 }
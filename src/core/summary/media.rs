@@ -0,0 +1,120 @@
+use crate::core::{
+    error::SummaryError,
+    request::{
+        ResponseWrapper,
+        content::{Content, ContentKind},
+    },
+    summary::{
+        def::{Player, SummaryResult},
+        utility::{format_size, text_clamp},
+    },
+};
+use lopdf::{Document, decode_text_string};
+use url::Url;
+
+// The dimensions are at the start of an image, but JPEG can have large EXIF and ICC segments in front of them
+const IMAGE_HEADER_LEN: usize = 64 * 1024;
+
+/// Summarizes a response that is not an HTML page from the file itself, described by its name, type and size
+pub async fn summarize(url: &Url, mut response: ResponseWrapper, content: &Content) -> Result<SummaryResult, SummaryError> {
+    let size = response.content_length();
+    let title = response.filename().unwrap_or_else(|| url.host_str().unwrap_or_default().to_string());
+
+    let mut summary = SummaryResult {
+        title: text_clamp(&title, 100),
+        sitename: url.domain().map(|domain| domain.to_string()),
+        ..Default::default()
+    };
+    let mut details = vec![label(content)];
+
+    match content.kind {
+        ContentKind::Image => {
+            if let Ok(dimensions) = imagesize::blob_size(response.peek(IMAGE_HEADER_LEN).await?) {
+                details.push(format!("{} × {}", dimensions.width, dimensions.height));
+            }
+            summary.thumbnail = Some(url.to_string());
+            summary.large_card = Some(true);
+        }
+        ContentKind::Video | ContentKind::Audio => {
+            summary.player = Player {
+                url: Some(url.to_string()),
+                width: None,
+                height: None,
+                allow: ["autoplay".to_string(), "encrypted-media".to_string(), "fullscreen".to_string()].to_vec(),
+            };
+        }
+        ContentKind::Pdf => {
+            let (title, author) = pdf_metadata(response).await;
+            if let Some(title) = title {
+                summary.title = text_clamp(&title, 100);
+            }
+            details.extend(author);
+        }
+        _ => {}
+    }
+
+    details.extend(size.map(format_size));
+    summary.description = Some(details.join(" · "));
+    Ok(summary)
+}
+
+// "PNG image" or "MP4 video", and the media type itself for other files
+fn label(content: &Content) -> String {
+    let noun = match content.kind {
+        ContentKind::Pdf => return "PDF document".to_string(),
+        ContentKind::Image => "image",
+        ContentKind::Video => "video",
+        ContentKind::Audio => "audio",
+        _ => return content.mime.clone().unwrap_or_else(|| "File".to_string()),
+    };
+
+    // image/svg+xml, video/x-matroska and image/vnd.microsoft.icon become SVG, MATROSKA and ICON
+    let format = content
+        .mime
+        .as_deref()
+        .and_then(|mime| mime.split('/').nth(1))
+        .and_then(|subtype| subtype.split('+').next())
+        .and_then(|subtype| subtype.rsplit('.').next())
+        .map(|subtype| subtype.trim_start_matches("x-").to_ascii_uppercase())
+        .filter(|format| !format.is_empty());
+
+    match format {
+        Some(format) => format!("{} {}", format, noun),
+        None => format!("{}{}", noun[..1].to_ascii_uppercase(), &noun[1..]),
+    }
+}
+
+// The document information dictionary can be anywhere in the file, so it has to be read as a whole.
+// A file that is too large or cannot be parsed still gets a summary, just without title and author.
+async fn pdf_metadata(response: ResponseWrapper) -> (Option<String>, Option<String>) {
+    let url = response.url().clone();
+    let bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::debug!("Skipping metadata of PDF '{}': {}", url, e);
+            return (None, None);
+        }
+    };
+
+    let document = match Document::load_mem(&bytes) {
+        Ok(document) => document,
+        Err(e) => {
+            tracing::debug!("Failed to parse PDF '{}': {}", url, e);
+            return (None, None);
+        }
+    };
+
+    let Ok(info) = document.get_dict_in_dict(&document.trailer, b"Info") else {
+        return (None, None);
+    };
+
+    let field = |key: &[u8]| {
+        info.get_deref(key, &document)
+            .ok()
+            .and_then(|value| decode_text_string(value).ok())
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    (field(b"Title"), field(b"Author"))
+}
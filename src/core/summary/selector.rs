@@ -66,5 +66,9 @@ pub static META_RATING_NAME: Lazy<Selector> = Lazy::new(|| s(r#"meta[name="ratin
 // oEmbed
 pub static LINK_JSON_OEMBED_TYPE: Lazy<Selector> = Lazy::new(|| s(r#"link[type="application/json+oembed"]"#));
 
+// Canonical URL
+pub static LINK_CANONICAL_REL: Lazy<Selector> = Lazy::new(|| s(r#"link[rel="canonical"]"#));
+pub static META_OG_URL_PROPERTY: Lazy<Selector> = Lazy::new(|| s(r#"meta[property="og:url"]"#));
+
 // iframe
 pub static IFRAME: Lazy<Selector> = Lazy::new(|| s("iframe"));
@@ -1,6 +1,8 @@
 use crate::core::{
-    request,
+    error::SummaryError,
+    request::{self, ResponseWrapper},
     summary::{
+        canonicalize::{canonicalize, is_same_site},
         def::*,
         selector,
         utility::{resolve_absolute_url, select_attr, select_text, text_clamp, url_exists_check},
@@ -16,7 +18,7 @@ pub async fn resolve_oembed(url: &Url, href: Option<String>, args: &SummarizeArg
 
     let href = resolve_absolute_url(url, &href.unwrap())?;
     let response = request::get(&href, &args.into()).await.ok()?;
-    let oembed = serde_json::from_str::<OEmbedData>(response.text().await?.as_str()).ok()?;
+    let oembed = serde_json::from_str::<OEmbedData>(response.text().await.ok()?.as_str()).ok()?;
 
     if oembed.version != "1.0" && oembed.r#type != "video" && oembed.r#type != "rich" {
         tracing::debug!("oembed type is not video or rich: {}", oembed.r#type);
@@ -147,22 +149,36 @@ pub fn resolve_player(url: &Url, html: &Html, search_with_twitter_player: bool)
     })
 }
 
-pub async fn generic_summarize(url: &Url, str_html: String, args: &SummarizeArguments) -> Option<SummaryResult> {
+/// Reads as much of the page as `handler` needs
+pub async fn read_html(response: ResponseWrapper, handler: &dyn SummarizeHandler) -> Result<String, SummaryError> {
+    if handler.reads_body() {
+        response.text().await
+    } else {
+        response.head_text().await
+    }
+}
+
+pub async fn generic_summarize(url: &Url, str_html: String, args: &SummarizeArguments) -> Result<SummaryResult, SummaryError> {
     execute_summarize(url, str_html, args, &GenericSummarizeHandler).await
 }
 
-pub async fn execute_summarize(url: &Url, str_html: String, args: &SummarizeArguments, handler: &dyn SummarizeHandler) -> Option<SummaryResult> {
+pub async fn execute_summarize(
+    url: &Url,
+    str_html: String,
+    args: &SummarizeArguments,
+    handler: &dyn SummarizeHandler,
+) -> Result<SummaryResult, SummaryError> {
     let html = Html::parse_document(str_html.as_str());
 
     let title = handler.title(url, &html);
     if title.is_none() {
         tracing::debug!("Title not found");
-        return None;
+        return Err(SummaryError::NoTitle);
     }
 
     let title = match htmlentity::entity::decode(title.unwrap().as_bytes()).to_string() {
         Ok(x) => text_clamp(&x, 100),
-        Err(_) => return None,
+        Err(_) => return Err(SummaryError::NoTitle),
     };
 
     let is_large_summary_image = handler.summary_large_image(url, &html);
@@ -201,8 +217,9 @@ pub async fn execute_summarize(url: &Url, str_html: String, args: &SummarizeArgu
     let activity_pub = handler.activity_pub(url, &html);
     let fediverse_creator = handler.fediverse_creator(url, &html);
     let sensitive = handler.sensitive(url, &html);
+    let canonical_url = handler.canonical_url(url, &html);
 
-    Some(SummaryResult {
+    Ok(SummaryResult {
         title,
         icon: if favicon_available { Some(favicon.unwrap().to_string()) } else { None },
         description,
@@ -213,7 +230,8 @@ pub async fn execute_summarize(url: &Url, str_html: String, args: &SummarizeArgu
         activity_pub,
         fediverse_creator,
         large_card: Some(is_large_summary_image),
-        url: None,
+        url: canonical_url,
+        redirects: None,
     })
 }
 
@@ -330,4 +348,14 @@ impl SummarizeHandler for GenericSummarizeHandler {
         );
         x.is_some_and(|v| v == "summary_large_image")
     }
+
+    fn canonical_url(&self, url: &Url, html: &Html) -> Option<String> {
+        let canonical = select_attr(html, "href", &[&selector::LINK_CANONICAL_REL])
+            .or_else(|| select_attr(html, "content", &[&selector::META_OG_URL_PROPERTY]))
+            .and_then(|u| resolve_absolute_url(url, &u))
+            .and_then(|u| Url::parse(&u).ok())
+            .filter(|u| matches!(u.scheme(), "http" | "https") && is_same_site(url, u))?;
+
+        Some(canonicalize(&canonical).to_string())
+    }
 }
@@ -1,13 +1,29 @@
 use sentry::SentryFutureExt;
+use std::{process, time::Duration};
 use tracing_subscriber::{filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
+mod cli;
 mod config;
 mod core;
 mod resource;
 mod server;
 
 fn main() {
-    let conf = &config::CONFIG;
+    if cli::ARGS.command == cli::Command::CheckConfig {
+        process::exit(check_config());
+    }
+
+    // Refuse to start on a configuration that would only fail later, or silently fall back to defaults
+    let report = config::validate::check(true);
+    for problem in &report.problems {
+        eprintln!("{}: {}", problem.severity, problem);
+    }
+    if report.has_errors() {
+        eprintln!("Invalid configuration, see the errors above");
+        process::exit(1);
+    }
+
+    let conf = config::CONFIG.load();
 
     // Logging setup
     let filter = tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
@@ -23,10 +39,11 @@ fn main() {
     });
 
     // Sentry setup
-    if core::sentry::is_sentry_enabled() {
+    // The guard has to outlive the server, dropping it disables the client
+    let sentry_guard = if core::sentry::is_sentry_enabled() {
         println!("Sentry logging is enabled");
 
-        let _guard = sentry::init(sentry::ClientOptions {
+        let guard = sentry::init(sentry::ClientOptions {
             dsn: core::sentry::SENTRY_DSN.clone(),
             release: sentry::release_name!(),
             ..Default::default()
@@ -37,14 +54,39 @@ fn main() {
             .with(filter)
             .with(sentry::integrations::tracing::layer())
             .init();
+
+        Some(guard)
     } else {
         tracing_subscriber::fmt().with_env_filter(filter).init();
-    }
+        None
+    };
 
     // Start server
     tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap().block_on(async {
         run().bind_hub(sentry::Hub::current()).await;
     });
+
+    if let Some(guard) = sentry_guard {
+        let flushed = guard.close(Some(Duration::from_secs(5)));
+        if !flushed {
+            tracing::warn!("Timed out while sending pending events to Sentry");
+        }
+    }
+}
+
+fn check_config() -> i32 {
+    let report = config::validate::check(false);
+    for problem in &report.problems {
+        println!("{}: {}", problem.severity, problem);
+    }
+
+    if report.has_errors() {
+        println!("Configuration is invalid");
+        1
+    } else {
+        println!("Configuration is valid");
+        0
+    }
 }
 
 async fn run() {
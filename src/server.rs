@@ -1,14 +1,18 @@
-use crate::{config, core};
+use crate::{
+    config,
+    core::{self, error::SummaryError},
+};
 use axum::{
-    Router,
+    Json, Router,
     body::Body,
     http::{Request, StatusCode},
     response::{IntoResponse, Response},
     routing,
 };
 use sentry::integrations::tower::NewSentryLayer;
-use std::time::Duration;
-use tokio::net::TcpListener;
+use serde::Serialize;
+use std::{sync::Arc, time::Duration};
+use tokio::{net::TcpListener, signal, sync::Notify};
 use tower_http::{
     request_id::{MakeRequestUuid, SetRequestIdLayer},
     trace::TraceLayer,
@@ -32,6 +36,10 @@ where E: Into<anyhow::Error>
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        if let Some(err) = self.0.downcast_ref::<SummaryError>() {
+            return err.clone().into_response();
+        }
+
         let request_id = Uuid::new_v4();
         tracing::error!(request_id = %request_id, "{}", self.0);
         (
@@ -44,14 +52,65 @@ impl IntoResponse for AppError {
 
 pub type AppResult<T> = Result<T, AppError>;
 
+#[derive(Serialize, Debug)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl From<&SummaryError> for ErrorBody {
+    fn from(err: &SummaryError) -> Self {
+        Self {
+            code: err.code(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for SummaryError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            SummaryError::InvalidLanguage(_) => StatusCode::BAD_REQUEST,
+            SummaryError::RobotsDisallowed | SummaryError::AclBlocked | SummaryError::TargetDenied => StatusCode::FORBIDDEN,
+            SummaryError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            SummaryError::UpstreamStatus(404 | 410) => StatusCode::NOT_FOUND,
+            SummaryError::UpstreamStatus(_) |
+            SummaryError::NetworkError(_) |
+            SummaryError::InvalidResponse(_) |
+            SummaryError::TooManyRedirects(_) |
+            SummaryError::RedirectLoop => StatusCode::BAD_GATEWAY,
+            SummaryError::UnsupportedUrl |
+            SummaryError::BodyTooLarge(_) |
+            SummaryError::ContentLengthRequired |
+            SummaryError::UnsupportedContentType(_) |
+            SummaryError::NoTitle => StatusCode::UNPROCESSABLE_ENTITY,
+            // Only produced by background refreshes, which never reach a client
+            SummaryError::NotModified => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status, Json(serde_json::json!({ "error": ErrorBody::from(&self) }))).into_response()
+    }
+}
+
 // Server setup
 pub async fn listen() {
-    let conf = &config::CONFIG;
+    let conf = config::CONFIG.load_full();
+
+    core::cache::init().await;
+    tokio::spawn(config::reload::watch());
 
     let mut app = Router::new()
         .route("/", route::index::handler())
         .route("/robots.txt", route::robots::handler())
+        .route("/healthz", routing::get(route::health::healthz))
+        .route("/readyz", routing::get(route::health::readyz))
         .route("/url", routing::get(route::url::handler))
+        .route("/urls", routing::post(route::urls::handler))
+        .route("/metrics", routing::get(route::metrics::handler))
+        .route("/admin/cache", routing::get(route::admin::lookup).delete(route::admin::delete))
+        .route("/admin/cache/refresh", routing::post(route::admin::refresh))
+        .route("/admin/cache/handlers/{id}", routing::delete(route::admin::purge_handler))
+        .route_layer(axum::middleware::from_fn(middleware::metrics::track))
         .layer(axum::middleware::from_fn(middleware::logger::request_logger))
         .layer(
             TraceLayer::new_for_http().on_response(|response: &Response, latency: Duration, _: &Span| {
@@ -82,8 +141,76 @@ pub async fn listen() {
 
     tracing::info!("Server listening on http://{}", addr);
 
-    let server = axum::serve(listener.unwrap(), app).await;
-    if let Err(err) = server {
-        tracing::error!("Server error: {}", err);
+    let shutdown_started = Arc::new(Notify::new());
+    let notify = Arc::clone(&shutdown_started);
+    let server = axum::serve(listener.unwrap(), app).with_graceful_shutdown(async move {
+        shutdown_signal().await;
+        notify.notify_one();
+    });
+
+    tokio::select! {
+        result = async {
+            let result = server.into_future().await;
+            // Refreshes started by the last requests would otherwise be cut off along with their cache writes
+            core::summary::drain_refreshes().await;
+            result
+        } => {
+            if let Err(err) = result {
+                tracing::error!("Server error: {}", err);
+            }
+        }
+        drain_timeout = async {
+            shutdown_started.notified().await;
+            let drain_timeout = Duration::from_millis(config::CONFIG.load().server.shutdown_timeout);
+            tokio::time::sleep(drain_timeout).await;
+            drain_timeout
+        } => {
+            tracing::warn!("In-flight requests did not finish within {:?}, shutting down anyway", drain_timeout);
+        }
+    }
+
+    core::cache::close().await;
+    tracing::info!("Server stopped");
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = signal::ctrl_c().await {
+            tracing::error!("Failed to listen for SIGINT: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut s) => {
+                s.recv().await;
+            }
+            Err(err) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    route::health::mark_shutting_down();
+    tracing::info!("Shutdown signal received");
+
+    // New connections are still accepted until the load balancer has seen /readyz fail
+    let delay = Duration::from_millis(config::CONFIG.load().server.shutdown_delay);
+    if !delay.is_zero() {
+        tracing::info!("Serving for another {:?} before draining", delay);
+        tokio::time::sleep(delay).await;
     }
+
+    tracing::info!("Draining in-flight requests");
 }
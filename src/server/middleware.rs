@@ -1 +1,2 @@
 pub mod logger;
+pub mod metrics;
@@ -0,0 +1,24 @@
+use crate::core::metrics;
+use axum::{body::Body, extract::MatchedPath, http::Request, middleware::Next, response::Response};
+use std::time::Instant;
+
+/// Records request counts and latency, labelled with the route pattern instead of the raw path to keep cardinality bounded
+pub async fn track(request: Request<Body>, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched".to_string(), |p| p.as_str().to_string());
+    let method = request.method().to_string();
+    let started_at = Instant::now();
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    let labels = [route.as_str(), method.as_str(), status.as_str()];
+    metrics::HTTP_REQUESTS_TOTAL.with_label_values(&labels).inc();
+    metrics::HTTP_REQUEST_DURATION
+        .with_label_values(&labels)
+        .observe(started_at.elapsed().as_secs_f64());
+
+    response
+}
@@ -1,3 +1,7 @@
+pub mod admin;
+pub mod health;
 pub mod index;
+pub mod metrics;
 pub mod robots;
 pub mod url;
+pub mod urls;
@@ -0,0 +1,114 @@
+use crate::{
+    config::CONFIG,
+    core::summary::{admin, def::SummarizeArguments},
+    server::{
+        AppResult,
+        route::url::{bearer_token, parse_target_url},
+    },
+};
+use axum::{
+    Json,
+    extract::{Path, Query},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use url::Url;
+use urlencoding::decode;
+
+#[derive(Deserialize, Debug)]
+pub struct EntryParams {
+    url: Option<String>,
+    lang: Option<String>,
+}
+
+/// The admin API uses its own token if configured and the secret key otherwise. Without either it is disabled.
+type Rejection = (StatusCode, &'static str);
+
+fn authorize(headers: &HeaderMap) -> Result<(), Rejection> {
+    let config = CONFIG.load();
+    let security = &config.security;
+    let token = if security.admin_token.is_empty() {
+        &security.secret_key
+    } else {
+        &security.admin_token
+    };
+
+    if token.is_empty() {
+        return Err((StatusCode::FORBIDDEN, "Admin API is disabled"));
+    }
+
+    if bearer_token(headers) != Some(token.as_str()) {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid admin token"));
+    }
+
+    Ok(())
+}
+
+fn target_url(params: &EntryParams) -> Result<Url, Rejection> {
+    let Some(url) = &params.url else {
+        return Err((StatusCode::BAD_REQUEST, "Missing 'url' parameter"));
+    };
+
+    let decoded = decode(url).map_err(|_| (StatusCode::BAD_REQUEST, "URL Decode failed"))?;
+    parse_target_url(&decoded).map_err(|message| (StatusCode::BAD_REQUEST, message))
+}
+
+pub async fn lookup(headers: HeaderMap, Query(params): Query<EntryParams>) -> AppResult<Response> {
+    if let Err(rejection) = authorize(&headers) {
+        return Ok(rejection.into_response());
+    }
+
+    let url = match target_url(&params) {
+        Ok(u) => u,
+        Err(rejection) => return Ok(rejection.into_response()),
+    };
+
+    match admin::lookup(&url, params.lang).await? {
+        Some(entry) => Ok(Json(entry).into_response()),
+        None => Ok((StatusCode::NOT_FOUND, "No cache entry for the URL").into_response()),
+    }
+}
+
+pub async fn delete(headers: HeaderMap, Query(params): Query<EntryParams>) -> AppResult<Response> {
+    if let Err(rejection) = authorize(&headers) {
+        return Ok(rejection.into_response());
+    }
+
+    let url = match target_url(&params) {
+        Ok(u) => u,
+        Err(rejection) => return Ok(rejection.into_response()),
+    };
+
+    if admin::delete(&url, params.lang).await? {
+        Ok(StatusCode::NO_CONTENT.into_response())
+    } else {
+        Ok((StatusCode::NOT_FOUND, "No cache entry for the URL").into_response())
+    }
+}
+
+pub async fn refresh(headers: HeaderMap, Query(params): Query<EntryParams>) -> AppResult<Response> {
+    if let Err(rejection) = authorize(&headers) {
+        return Ok(rejection.into_response());
+    }
+
+    let url = match target_url(&params) {
+        Ok(u) => u,
+        Err(rejection) => return Ok(rejection.into_response()),
+    };
+
+    match admin::refresh(SummarizeArguments::new(url, params.lang)).await? {
+        Some(entry) => Ok(Json(entry).into_response()),
+        // The result was not stored, either because the cache is disabled or upstream forbids storing it
+        None => Ok((StatusCode::ACCEPTED, "Refreshed, but the result was not cached").into_response()),
+    }
+}
+
+pub async fn purge_handler(headers: HeaderMap, Path(handler_id): Path<String>) -> AppResult<Response> {
+    if let Err(rejection) = authorize(&headers) {
+        return Ok(rejection.into_response());
+    }
+
+    let deleted = admin::purge_handler(&handler_id).await;
+    Ok(Json(serde_json::json!({ "deleted": deleted })).into_response())
+}
@@ -0,0 +1,83 @@
+use crate::core::{cache, request};
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use serde::Serialize;
+use std::{
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Makes `/readyz` fail from now on so that the load balancer stops sending new requests while we drain
+pub fn mark_shutting_down() {
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Ok,
+    Error,
+    Disabled,
+}
+
+#[derive(Serialize, Debug)]
+struct CheckResult {
+    status: CheckStatus,
+    latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct Checks {
+    cache: CheckResult,
+    dns: CheckResult,
+}
+
+#[derive(Serialize, Debug)]
+struct Readiness {
+    status: &'static str,
+    checks: Checks,
+}
+
+/// Liveness only tells that the process is able to serve requests, dependencies are checked by `/readyz`
+pub async fn healthz() -> impl IntoResponse {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+pub async fn readyz() -> impl IntoResponse {
+    let (cache, dns) = tokio::join!(check(cache::ping()), check(async { Some(request::check_dns_resolver()) }));
+    let checks = Checks { cache, dns };
+
+    let ready = [&checks.cache, &checks.dns].iter().all(|c| c.status != CheckStatus::Error);
+    let (status_code, status) = if SHUTTING_DOWN.load(Ordering::SeqCst) {
+        (StatusCode::SERVICE_UNAVAILABLE, "shutting_down")
+    } else if ready {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "degraded")
+    };
+
+    (status_code, Json(Readiness { status, checks }))
+}
+
+/// Runs a single check and measures how long it took. A check that returns `None` is disabled by the configuration.
+async fn check<F>(future: F) -> CheckResult
+where F: Future<Output = Option<anyhow::Result<()>>> {
+    let started_at = Instant::now();
+    let result = future.await;
+    let latency_ms = started_at.elapsed().as_secs_f64() * 1000.0;
+
+    let (status, error) = match result {
+        None => (CheckStatus::Disabled, None),
+        Some(Ok(_)) => (CheckStatus::Ok, None),
+        Some(Err(e)) => {
+            tracing::warn!("Readiness check failed: {}", e);
+            (CheckStatus::Error, Some(e.to_string()))
+        }
+    };
+
+    CheckResult { status, latency_ms, error }
+}
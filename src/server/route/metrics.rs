@@ -0,0 +1,36 @@
+use crate::{
+    config::CONFIG,
+    core::metrics,
+    server::route::url::{bearer_token, is_authorized},
+};
+use axum::{
+    extract::Query,
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct ReqParams {
+    token: Option<String>,
+}
+
+/// Accepts the token as a bearer token or as the `token` query parameter.
+/// If no dedicated metrics token is configured the secret key protects the endpoint instead.
+fn is_metrics_authorized(token: Option<&str>) -> bool {
+    let config = CONFIG.load();
+    let metrics_token = &config.security.metrics_token;
+    if metrics_token.is_empty() {
+        return is_authorized(token);
+    }
+
+    token == Some(metrics_token.as_str())
+}
+
+pub async fn handler(headers: HeaderMap, Query(params): Query<ReqParams>) -> impl IntoResponse {
+    if !is_metrics_authorized(bearer_token(&headers).or(params.token.as_deref())) {
+        return (StatusCode::UNAUTHORIZED, "Invalid metrics token").into_response();
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], metrics::render()).into_response()
+}
@@ -3,11 +3,17 @@ use crate::{
     core::summary::{def::SummarizeArguments, summary},
     server::AppResult,
 };
-use axum::{Json, extract::Query, http::HeaderMap, response::IntoResponse};
+use axum::{
+    extract::Query,
+    http::{HeaderMap, HeaderValue, header},
+    response::IntoResponse,
+};
+use language_tags::LanguageTag;
 use reqwest::StatusCode;
 use serde::Deserialize;
 use url::Url;
 use urlencoding::decode;
+use xxhash_rust::xxh64::xxh64;
 
 #[derive(Deserialize, Debug)]
 pub struct ReqParams {
@@ -15,32 +21,95 @@ pub struct ReqParams {
     lang: Option<String>,
     #[serde(rename = "userAgent")]
     user_agent: Option<String>,
-    // #[serde(rename = "followRedirects")]
-    // follow_redirects: Option<bool>,
-    // #[serde(rename = "responseTimeout")]
-    // response_timeout: Option<u64>,
-    // #[serde(rename = "operationTimeout")]
-    // operation_timeout: Option<u64>,
-    // #[serde(rename = "contentLengthLimit")]
-    // content_length_limit: Option<usize>,
-    // #[serde(rename = "contentLengthRequired")]
-    // content_length_required: Option<bool>,
+    #[serde(rename = "followRedirects")]
+    follow_redirects: Option<bool>,
+    #[serde(rename = "responseTimeout")]
+    response_timeout: Option<u64>,
+    #[serde(rename = "operationTimeout")]
+    operation_timeout: Option<u64>,
+    #[serde(rename = "contentLengthLimit")]
+    content_length_limit: Option<usize>,
+    #[serde(rename = "contentLengthRequired")]
+    content_length_required: Option<bool>,
+    #[serde(rename = "includeRedirects")]
+    include_redirects: Option<bool>,
     #[serde(rename = "secretKey")]
     secret_key: Option<String>,
 }
 
-pub async fn handler(Query(params): Query<ReqParams>) -> AppResult<impl IntoResponse> {
+pub fn is_authorized(provided_key: Option<&str>) -> bool {
+    let config = CONFIG.load();
+    let secret_key = &config.security.secret_key;
+    secret_key.is_empty() || provided_key == Some(secret_key.as_str())
+}
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+pub fn parse_target_url(url: &str) -> Result<Url, &'static str> {
+    let url = Url::parse(url).map_err(|_| "Invalid URL")?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("Only http and https are supported");
+    }
+
+    Ok(url)
+}
+
+/// Picks the most preferred valid language tag from an `Accept-Language` header
+fn preferred_language(headers: &HeaderMap) -> Option<String> {
+    let accept_language = headers.get(header::ACCEPT_LANGUAGE)?.to_str().ok()?;
+    let mut preferred: Option<(&str, f32)> = None;
+
+    for entry in accept_language.split(',') {
+        let mut parts = entry.split(';');
+        let tag = parts.next().unwrap_or_default().trim();
+        let quality = parts
+            .find_map(|p| p.trim().strip_prefix("q="))
+            .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok());
+
+        let Some(quality) = quality else { continue };
+        if tag == "*" || quality <= 0.0 || LanguageTag::parse(tag).is_err() {
+            continue;
+        }
+
+        if preferred.is_none_or(|(_, q)| quality > q) {
+            preferred = Some((tag, quality));
+        }
+    }
+
+    preferred.map(|(tag, _)| tag.to_string())
+}
+
+fn cache_control(ttl: Option<u64>) -> HeaderValue {
+    match ttl {
+        Some(ttl) => format!("public, max-age={ttl}").parse().unwrap(),
+        None => HeaderValue::from_static("no-store"),
+    }
+}
+
+/// Weak comparison as required for `If-None-Match` (RFC 9110 section 13.1.2)
+fn etag_matches(request_headers: &HeaderMap, etag: &str) -> bool {
+    request_headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+pub async fn handler(request_headers: HeaderMap, Query(params): Query<ReqParams>) -> AppResult<impl IntoResponse> {
     let url_str = params.url;
     if url_str.is_none() {
         return Ok((StatusCode::BAD_REQUEST, "Missing 'url' parameter").into_response());
     }
 
-    let secret_key = &CONFIG.security.secret_key;
-    if !secret_key.is_empty() {
-        let provided_key = params.secret_key;
-        if provided_key.is_none() || provided_key.unwrap() != *secret_key {
-            return Ok((StatusCode::UNAUTHORIZED, "Invalid secret key").into_response());
-        }
+    if !is_authorized(params.secret_key.as_deref()) {
+        return Ok((StatusCode::UNAUTHORIZED, "Invalid secret key").into_response());
     }
 
     let url_string = url_str.unwrap();
@@ -49,38 +118,56 @@ pub async fn handler(Query(params): Query<ReqParams>) -> AppResult<impl IntoResp
         return Ok((StatusCode::BAD_REQUEST, "URL Decode failed").into_response());
     }
 
-    let url = Url::parse(decoded_url.unwrap().as_ref());
-    if url.is_err() {
-        return Ok((StatusCode::BAD_REQUEST, "Invalid URL").into_response());
-    }
-    let url = url.unwrap();
+    let url = match parse_target_url(decoded_url.unwrap().as_ref()) {
+        Ok(u) => u,
+        Err(message) => return Ok((StatusCode::BAD_REQUEST, message).into_response()),
+    };
 
-    let scheme = url.scheme();
-    if !matches!(scheme, "http" | "https") {
-        return Ok((StatusCode::BAD_REQUEST, "Only http and https are supported").into_response());
-    }
+    // Without an explicit `lang` the response depends on Accept-Language, even if the client did not send one
+    let lang_from_header = params.lang.is_none() && CONFIG.load().general.accept_language;
+    let lang = if lang_from_header {
+        preferred_language(&request_headers)
+    } else {
+        params.lang
+    };
 
     let arguments = SummarizeArguments {
         url: url.clone(),
-        lang: params.lang,
+        lang,
         user_agent: params.user_agent,
-        // follow_redirects: params.follow_redirects,
-        // response_timeout: params.response_timeout,
-        // operation_timeout: params.operation_timeout,
-        // content_length_limit: params.content_length_limit,
-        // content_length_required: params.content_length_required,
+        follow_redirects: params.follow_redirects,
+        response_timeout: params.response_timeout,
+        operation_timeout: params.operation_timeout,
+        content_length_limit: params.content_length_limit,
+        content_length_required: params.content_length_required,
+        validators: None,
     };
 
+    let outcome = summary(arguments).await;
+
     let mut headers = HeaderMap::new();
-    let summary = summary(arguments).await;
+    headers.insert(header::CACHE_CONTROL, cache_control(outcome.ttl));
+    if lang_from_header {
+        headers.insert(header::VARY, HeaderValue::from_static("Accept-Language"));
+    }
 
-    if summary.is_none() {
-        headers.insert("Cache-Control", "public, max-age=3600".parse().unwrap());
-        return Ok((StatusCode::UNPROCESSABLE_ENTITY, headers, "Failed to summarize the URL").into_response());
+    let mut summary = match outcome.result {
+        Ok(s) => s,
+        Err(err) => return Ok((headers, err).into_response()),
+    };
+
+    if params.include_redirects != Some(true) {
+        summary.redirects = None;
     }
 
-    let response = Json(summary.unwrap()).into_response();
+    let body = serde_json::to_vec(&summary)?;
+    let etag = format!("\"{:016x}\"", xxh64(&body, 0));
+    headers.insert(header::ETAG, etag.parse().unwrap());
+
+    if etag_matches(&request_headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, headers).into_response());
+    }
 
-    headers.insert("Cache-Control", "public, max-age=604800".parse().unwrap());
-    Ok((headers, response).into_response())
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    Ok((headers, body).into_response())
 }
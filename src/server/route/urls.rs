@@ -0,0 +1,177 @@
+use crate::{
+    config::CONFIG,
+    core::summary::{
+        def::{SummarizeArguments, SummaryResult},
+        summary,
+    },
+    server::{
+        AppResult, ErrorBody,
+        route::url::{is_authorized, parse_target_url},
+    },
+};
+use axum::{
+    Json,
+    body::Body,
+    response::{
+        IntoResponse,
+        sse::{Event, Sse},
+    },
+};
+use futures::{StreamExt, stream};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+
+#[derive(Deserialize, Debug)]
+pub struct ReqBody {
+    urls: Vec<BatchItem>,
+    #[serde(flatten)]
+    options: SharedOptions,
+    #[serde(rename = "secretKey")]
+    secret_key: Option<String>,
+    stream: Option<StreamMode>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum BatchItem {
+    Url(String),
+    Detailed {
+        url: String,
+        lang: Option<String>,
+        #[serde(rename = "userAgent")]
+        user_agent: Option<String>,
+    },
+}
+
+/// Options applied to every URL in the batch, `lang` and `userAgent` can be overridden per item
+#[derive(Deserialize, Debug, Clone)]
+struct SharedOptions {
+    lang: Option<String>,
+    #[serde(rename = "userAgent")]
+    user_agent: Option<String>,
+    #[serde(rename = "followRedirects")]
+    follow_redirects: Option<bool>,
+    #[serde(rename = "responseTimeout")]
+    response_timeout: Option<u64>,
+    #[serde(rename = "operationTimeout")]
+    operation_timeout: Option<u64>,
+    #[serde(rename = "contentLengthLimit")]
+    content_length_limit: Option<usize>,
+    #[serde(rename = "contentLengthRequired")]
+    content_length_required: Option<bool>,
+    #[serde(rename = "includeRedirects")]
+    include_redirects: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum StreamMode {
+    Ndjson,
+    Sse,
+}
+
+#[derive(Serialize, Debug)]
+struct BatchResult {
+    index: usize,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<SummaryResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorBody>,
+}
+
+impl BatchResult {
+    fn failed(index: usize, url: String, error: ErrorBody) -> Self {
+        Self {
+            index,
+            url,
+            summary: None,
+            error: Some(error),
+        }
+    }
+}
+
+pub async fn handler(Json(body): Json<ReqBody>) -> AppResult<impl IntoResponse> {
+    if !is_authorized(body.secret_key.as_deref()) {
+        return Ok((StatusCode::UNAUTHORIZED, "Invalid secret key").into_response());
+    }
+
+    let config = CONFIG.load_full();
+    let max_urls = config.general.batch_max_urls;
+    if body.urls.len() > max_urls {
+        return Ok((StatusCode::BAD_REQUEST, format!("Too many URLs, up to {max_urls} are allowed")).into_response());
+    }
+
+    let options = body.options;
+    let concurrency = config.general.batch_concurrency.max(1);
+    let results = stream::iter(body.urls.into_iter().enumerate()).map(move |(index, item)| process(index, item, options.clone()));
+
+    let response = match body.stream {
+        None => {
+            let results = results.buffered(concurrency).collect::<Vec<_>>().await;
+            Json(results).into_response()
+        }
+        Some(StreamMode::Ndjson) => {
+            let lines = results.buffer_unordered(concurrency).map(|result| {
+                let mut line = serde_json::to_string(&result).unwrap_or_default();
+                line.push('\n');
+                Ok::<_, Infallible>(line)
+            });
+
+            ([("Content-Type", "application/x-ndjson")], Body::from_stream(lines)).into_response()
+        }
+        Some(StreamMode::Sse) => {
+            let events = results.buffer_unordered(concurrency).map(|result| Event::default().json_data(result));
+            Sse::new(events).into_response()
+        }
+    };
+
+    Ok(response)
+}
+
+async fn process(index: usize, item: BatchItem, options: SharedOptions) -> BatchResult {
+    let (url, lang, user_agent) = match item {
+        BatchItem::Url(url) => (url, options.lang, options.user_agent),
+        BatchItem::Detailed { url, lang, user_agent } => (url, lang.or(options.lang), user_agent.or(options.user_agent)),
+    };
+
+    let parsed_url = match parse_target_url(&url) {
+        Ok(u) => u,
+        Err(message) => {
+            let error = ErrorBody {
+                code: "INVALID_URL",
+                message: message.to_string(),
+            };
+            return BatchResult::failed(index, url, error);
+        }
+    };
+
+    let arguments = SummarizeArguments {
+        url: parsed_url,
+        lang,
+        user_agent,
+        follow_redirects: options.follow_redirects,
+        response_timeout: options.response_timeout,
+        operation_timeout: options.operation_timeout,
+        content_length_limit: options.content_length_limit,
+        content_length_required: options.content_length_required,
+        validators: None,
+    };
+
+    match summary(arguments).await.result {
+        Ok(mut summary) => {
+            if options.include_redirects != Some(true) {
+                summary.redirects = None;
+            }
+
+            BatchResult {
+                index,
+                url,
+                summary: Some(summary),
+                error: None,
+            }
+        }
+        Err(err) => BatchResult::failed(index, url, ErrorBody::from(&err)),
+    }
+}